@@ -1,63 +1,212 @@
 //! This relative simple collision module owns `CollisionWorld` and related structs. With these, you
 //! can create bodies and allow the system to manage their interactions.
 
+pub mod layers;
+pub mod replay;
+pub mod snapshot;
+pub mod tiles;
+
 use std::time::Duration;
 
+use self::layers::{Layer, LayerMask, LayerRegistry};
+use self::replay::StateHasher;
+use self::snapshot::WorldSnapshot;
+use self::tiles::{TileFlags, TileLayer};
 use crate::grid::GridCoord;
 use crate::{
     geom::{P2, V2},
     grid::Grid,
     grid::GridRegion,
-    pool::{Handle as PoolHandle, Pool},
-    shape::{Rect, RectSide},
+    pool::{Handle, Pool},
+    shape::{Ray, Rect, RectSide, RectSides, Shape},
 };
+use std::cell::{RefCell, RefMut};
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::hash::{Hash, Hasher};
+use std::mem;
+
+/// Handles into the pool of bodies, used internally; see `BodyHandle` for the public version.
+type PoolHandle = Handle<Body>;
+
+/// How a `Body` interacts with the other bodies it collides with.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BodyKind {
+    /// A body which blocks, and is blocked by, other solid bodies.
+    Solid,
+    /// A body which never blocks anything nor is blocked by anything, but which still reports
+    /// contact events (see `CollisionWorld::drain_events`) for bodies that overlap it. Useful for
+    /// doors, pickups, damage zones, and the like.
+    Sensor,
+    /// A solid body which, after being pushed against for long enough, slides a fixed distance in
+    /// the direction it was pushed (assuming nothing is in its way), e.g. for block puzzles. See
+    /// `PushParams` for more details.
+    Pushable,
+    /// A body which moves on its own, by its velocity or along a path, and which nothing can
+    /// stop: bodies in its way are pushed aside, and bodies standing on it are carried along with
+    /// it, e.g. for moving platforms, rafts, and conveyor belts. A body pinned against a wall is
+    /// squeezed out sideways, or crushed if it can't be (see `CollisionWorld::drain_crushed`).
+    /// See `KinematicParams` for more details.
+    Kinematic,
+}
+
+/// Settings for a `BodyKind::Pushable` body.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PushParams {
+    /// How long another body has to keep pushing against this one before it starts to move.
+    pub delay: Duration,
+    /// How far this body moves each time it is pushed. It always ends up at a multiple of this
+    /// along the axis it was pushed, so e.g. a block the size of a tile stays lined up with the
+    /// tiles around it.
+    pub distance: f32,
+    /// How fast this body moves, in units per second, once it's been pushed.
+    pub speed: f32,
+}
 
-const fn group(i: u32) -> u32 {
-    1 << i
+/// Progress made on pushing a `BodyKind::Pushable` body.
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct PushState {
+    params: PushParams,
+    /// The direction the body is currently being pushed in, if any
+    push_dir: Option<V2>,
+    /// How long the body has been pushed in `push_dir` without stopping
+    push_duration: Duration,
+    /// Where the body is moving to after being pushed, if it is moving at all
+    target: Option<P2>,
+}
+
+/// Settings for a `BodyKind::Kinematic` body.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KinematicParams {
+    /// Bodies in any of these layers are carried along while standing on this body, meaning their
+    /// center is inside it. Riders usually shouldn't collide with what they ride on (see
+    /// `CollisionWorldParams::layer_pairs`), or they'd never be able to stand on it at all.
+    ///
+    /// Carried bodies are still blocked by everything else, so a rider carried into a wall stays
+    /// put against it while this body slides out from under it.
+    pub carries: LayerMask,
+    /// Extra velocity given to bodies riding this one, on top of however this body moves itself,
+    /// e.g. for a conveyor belt.
+    pub surface_vel: V2,
+    /// Points for this body to travel between, in order, looping back to the first one after the
+    /// last. If empty, this body moves by its `vel` instead, like any other.
+    pub path: Vec<P2>,
+    /// How fast this body travels along `path`, in units per second.
+    pub path_speed: f32,
+}
+
+/// Progress made by a `BodyKind::Kinematic` body along its path.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+struct KinematicState {
+    params: KinematicParams,
+    /// The index of the point in `params.path` that the body is heading towards
+    next_point: usize,
+}
+
+impl KinematicState {
+    /// Returns how far a kinematic body at `pos`, moving at `vel`, moves over the next
+    /// `time_step_secs`, updating its progress along its path if it has one.
+    fn advance(&mut self, pos: P2, vel: V2, time_step_secs: f32) -> V2 {
+        let path = &self.params.path;
+        if path.is_empty() {
+            return vel * time_step_secs;
+        }
+
+        let mut pos_next = pos;
+        let mut remaining = self.params.path_speed * time_step_secs;
+        // If points are close enough together, we might pass several of them in a single step
+        for _ in 0..path.len() {
+            let to_point = path[self.next_point] - pos_next;
+            let dist = to_point.len();
+            if dist > remaining {
+                pos_next += to_point.normalized() * remaining;
+                break;
+            }
+            pos_next = path[self.next_point];
+            remaining -= dist;
+            self.next_point = (self.next_point + 1) % path.len();
+        }
+        pos_next - pos
+    }
 }
-pub const GROUP_0: u32 = group(0);
-pub const GROUP_1: u32 = group(1);
-pub const GROUP_2: u32 = group(2);
-pub const GROUP_3: u32 = group(3);
-pub const GROUP_4: u32 = group(4);
-pub const GROUP_5: u32 = group(5);
-pub const GROUP_6: u32 = group(6);
-pub const GROUP_7: u32 = group(7);
-pub const GROUP_8: u32 = group(8);
-pub const GROUP_9: u32 = group(9);
-pub const GROUP_10: u32 = group(10);
-pub const GROUP_11: u32 = group(11);
-pub const GROUP_12: u32 = group(12);
-pub const GROUP_13: u32 = group(13);
-pub const GROUP_14: u32 = group(14);
-pub const GROUP_15: u32 = group(15);
-pub const GROUP_16: u32 = group(16);
-pub const GROUP_17: u32 = group(17);
-pub const GROUP_18: u32 = group(18);
-pub const GROUP_19: u32 = group(19);
-pub const GROUP_20: u32 = group(20);
-pub const GROUP_21: u32 = group(21);
-pub const GROUP_22: u32 = group(22);
-pub const GROUP_23: u32 = group(23);
-pub const GROUP_25: u32 = group(25);
-pub const GROUP_26: u32 = group(26);
-pub const GROUP_27: u32 = group(27);
-pub const GROUP_28: u32 = group(28);
-pub const GROUP_29: u32 = group(29);
-pub const GROUP_30: u32 = group(30);
-pub const GROUP_31: u32 = group(31);
 
 /// An object in space which can interact with other objects. A `Body` should act as the source of
 /// truth for a game object's position in the world, as it will respect the space taken up by other
 /// bodies.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Body {
-    pub group: u32,
+    pub kind: BodyKind,
+    /// The layers this body belongs to, which decide what it collides with. See
+    /// `CollisionWorldParams::layer_pairs`.
+    pub layers: LayerMask,
+    /// The shape of this body, which fits inside the rect specified by `pos` and `size`.
+    pub shape: Shape,
+    /// The sides of this body which block other bodies running into them. Bodies coming from any
+    /// other direction pass right through, so e.g. `RectSides::BOTTOM` makes a ledge that can be
+    /// hopped down off of but not climbed back up.
+    pub blocks: RectSides,
     pub pos: P2,
+    /// Where this body was at the start of the last time step. See `interpolated_pos`.
+    pub prev_pos: P2,
     pub size: V2,
     pub vel: V2,
+    /// How much `vel` changes by every second, e.g. while speeding up to a run.
+    pub accel: V2,
+    /// How quickly `vel` decays on its own, e.g. for knockback that fades out or bodies drifting
+    /// in water. Every second, `vel` is multiplied by `e^-damping`.
+    pub damping: f32,
+    /// How much speed is lost every second, on top of `damping`, e.g. high for rough ground and
+    /// low for ice. Unlike `accel`, friction only ever slows a body down to a stop, never reversing
+    /// it.
+    pub friction: f32,
+    /// The fastest `accel` can make this body go. Impulses and setting `vel` directly can still
+    /// take a body past it (see `CollisionWorld::apply_impulse`), after which it'll only slow
+    /// back down through `damping` and `friction`.
+    pub max_speed: Option<f32>,
+    /// Whatever the game wants to attach to this body, e.g. the id of the entity it belongs to, so
+    /// that bodies returned by queries can be traced back to it. Never used by the world itself.
+    pub user_data: u64,
+}
+
+impl Body {
+    /// Update `vel` to account for `accel`, `damping`, `friction` and `max_speed` over
+    /// `time_step_secs`.
+    fn integrate_vel(&mut self, time_step_secs: f32) {
+        let speed_t0 = self.vel.len();
+        let mut vel = self.vel + self.accel * time_step_secs;
+        if let Some(max_speed) = self.max_speed {
+            let speed = vel.len();
+            let max_speed = max_speed.max(speed_t0);
+            if speed > max_speed {
+                vel *= max_speed / speed;
+            }
+        }
+
+        vel *= (-self.damping * time_step_secs).exp();
+        let speed = vel.len();
+        let friction = self.friction * time_step_secs;
+        self.vel = if speed > friction {
+            vel * ((speed - friction) / speed)
+        } else {
+            V2::zero()
+        };
+    }
+
+    /// Returns where this body is between its last position and its current one, with `alpha`
+    /// ranging from 0 (at `prev_pos`) to 1 (at `pos`). Pass in
+    /// `CollisionWorld::interpolation_alpha` to smoothly render a body between time steps.
+    pub fn interpolated_pos(&self, alpha: f32) -> P2 {
+        self.prev_pos + (self.pos - self.prev_pos) * alpha
+    }
 }
 
 impl PartialEq for Body {
@@ -70,19 +219,102 @@ impl Eq for Body {}
 
 /// A handle to a `Body`, which `CollisionWorld` creates for you when you ask it to create a body. You
 /// use the handle to safely query / remove bodies.
-#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct BodyHandle {
     inner_handle: PoolHandle, // Our own handle just delegates all work
 }
 
+/// What stage of a contact between two bodies a `ContactEvent` is reporting.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ContactKind {
+    /// The two bodies just started touching this time step.
+    Began,
+    /// The two bodies were already touching last time step and still are.
+    Persisted,
+    /// The two bodies were touching last time step but aren't anymore (including if one of them
+    /// was removed).
+    Ended,
+}
+
+/// A notification that two bodies which are able to collide with each other are touching. See
+/// `CollisionWorld::drain_events` for more details.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ContactEvent {
+    pub kind: ContactKind,
+    /// The body that initiated the contact, i.e. the one that was moving (although it's possible
+    /// that `other` was moving too).
+    pub body: BodyHandle,
+    pub other: BodyHandle,
+    /// The side of `other` that `body` is touching.
+    pub side: RectSide,
+    /// A unit vector pointing out of `other` at the point of contact, towards `body`.
+    pub normal: V2,
+}
+
+impl ContactEvent {
+    fn new(kind: ContactKind, handle: PoolHandle, other: PoolHandle, side: RectSide) -> Self {
+        ContactEvent {
+            kind,
+            body: BodyHandle {
+                inner_handle: handle,
+            },
+            other: BodyHandle {
+                inner_handle: other,
+            },
+            side,
+            normal: side.normal(),
+        }
+    }
+}
+
+/// Where a body ended up after `CollisionWorld::move_and_slide`, and what it ran into on the way.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MoveResult {
+    pub pos: P2,
+    pub contacts: Vec<MoveContact>,
+}
+
+/// Something a body ran into during `CollisionWorld::move_and_slide`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MoveContact {
+    /// The body that was hit, or `None` if it was a tile instead (see `CollisionWorld::set_tiles`).
+    pub body: Option<BodyHandle>,
+    /// The side of whatever was hit that the moving body ran into.
+    pub side: RectSide,
+    /// A unit vector pointing out of whatever was hit, towards the moving body.
+    pub normal: V2,
+}
+
+/// A body hit by a ray. See `CollisionWorld::raycast` for more details.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RayHit {
+    /// The body that was hit, or `None` if the ray hit a tile instead (see
+    /// `CollisionWorld::set_tiles`).
+    pub body: Option<BodyHandle>,
+    /// Where the ray first hit the body.
+    pub point: P2,
+    /// A unit vector pointing out of the side of the body that was hit. This will be the zero
+    /// vector if the ray started inside the body.
+    pub normal: V2,
+    /// How far along the ray `point` is.
+    pub distance: f32,
+}
+
 /// An owner of several bodies. After creating one and adding several bodies to it, use
 /// `elapse_time` to update the world's state frame by frame.
 pub struct CollisionWorld {
     time_counter: Duration,
     time_step: Duration,
     bodies: Pool<Body>,
-    /// A mapping of the source group to all groups they can collide with
-    group_masks: HashMap<u32, u32>,
+    layers: LayerRegistry,
+    /// For each layer (by index), all the layers it can collide with
+    collision_masks: [LayerMask; Layer::COUNT as usize],
+    /// For each layer (by index), all the layers it gives way to
+    yield_masks: [LayerMask; Layer::COUNT as usize],
+    corner_tolerance: f32,
     /// How large we want our grid partitions to be. This is an optimization as it allows us to only
     /// check our own (and nearby) partitions for object we might collide with, potentially ignoring
     /// many others. Larger partitions use less memory but smaller partitions should provide a
@@ -92,12 +324,33 @@ pub struct CollisionWorld {
     /// profiling / experimentation may be worthwhile here.
     partition_size: [f32; 2],
     grid: Grid<PoolHandle>,
-    /// Users may modify bodies externally by accessing them through mutating getters. In those
-    /// cases, we attempt to refresh them next time we get a chance.
-    refresh_handles: HashSet<PoolHandle>,
+    /// Bodies which were handed out through `body_mut` or `bodies_mut`, and so may have been
+    /// changed in any way. Their bookkeeping is brought up to date the next time `elapse_time` is
+    /// called. Changes made through `set_pos`, `set_vel`, etc. are kept up to date immediately.
+    dirty_handles: BTreeSet<PoolHandle>,
+    /// Bodies which may have moved since the start of the last time step, and so will need their
+    /// `prev_pos` updated at the start of the next one.
+    moved_handles: BTreeSet<PoolHandle>,
     /// We keep track of moving bodies, since they are the only ones that can initiate a collision;
     /// in our update loop, we only have to process what they are doing.
-    moving_handles: HashSet<PoolHandle>,
+    moving_handles: BTreeSet<PoolHandle>,
+    /// All pairs of bodies that were touching at the end of the last time step, used to figure
+    /// out which contacts began and ended. The first handle is the body which initiated contact.
+    contacts: BTreeMap<(PoolHandle, PoolHandle), RectSide>,
+    events: Vec<ContactEvent>,
+    tiles: Option<TileLayer>,
+    pushables: BTreeMap<PoolHandle, PushState>,
+    kinematics: BTreeMap<PoolHandle, KinematicState>,
+    /// Pushable bodies which finished moving this update. See `drain_settled`.
+    settled: Vec<BodyHandle>,
+    /// Bodies which kinematic bodies had no room to push out of their way this update. See
+    /// `drain_crushed`.
+    crushed: Vec<BodyHandle>,
+    /// Scratch space for `query_grid` and `cast_ray`, kept between calls so that queries don't
+    /// allocate every time.
+    grid_buffer: RefCell<Vec<PoolHandle>>,
+    ray_hits: RefCell<Vec<RayHit>>,
+    ray_visited: RefCell<HashSet<PoolHandle>>,
 }
 
 impl<'b> From<&'b Body> for Rect {
@@ -107,11 +360,54 @@ impl<'b> From<&'b Body> for Rect {
 }
 
 pub struct CollisionWorldParams {
-    /// A list of group pairs that can collide with each other. This relationship is automatically
+    /// Every layer that bodies (and tiles) in the world may belong to. Using a layer which isn't
+    /// registered here is an error.
+    pub layers: LayerRegistry,
+    /// A list of layer pairs that can collide with each other. This relationship is automatically
     /// symmetric: If `A` can collide with `B` then `B` will also collide with `A`
-    pub group_pairs: Vec<[u32; 2]>,
+    pub layer_pairs: Vec<[Layer; 2]>,
+    /// Like `layer_pairs`, but only in one direction: for each pair, `[A, B]`, bodies in layer `A`
+    /// are blocked by bodies in layer `B`, while `B` bodies pass right through `A` bodies.
+    pub one_way_layer_pairs: Vec<[Layer; 2]>,
+    /// A list of layer pairs, `[A, B]`, where bodies in layer `A` give way to bodies in layer `B`.
+    /// Instead of blocking `B`, an `A` body that gets run into will be pushed along ahead of it (as
+    /// long as there's room for it to move). This relationship is NOT symmetric; if both `[A, B]`
+    /// and `[B, A]` are registered, bodies in either layer will push each other around.
+    ///
+    /// Layers in a pair must also be registered in `layer_pairs` for this to have any effect.
+    pub yield_pairs: Vec<[Layer; 2]>,
+    /// How far a body moving straight along one axis may be nudged sideways to slip past the
+    /// corner of something blocking it, or 0 to disable this entirely.
+    ///
+    /// Without this, a body that's misaligned with a gap (e.g. a doorway) by just a pixel or two
+    /// will get stuck on its edge, which feels bad when it's being controlled by a player.
+    pub corner_tolerance: f32,
     /// See comment for `CollisionWorld.partition_size`
     pub partition_size: [f32; 2],
+    /// How much time passes in a single step of the simulation. However much time is passed into
+    /// `CollisionWorld::elapse_time`, bodies are always moved in increments of exactly this much,
+    /// keeping the simulation stable no matter the frame rate. 16.67 ms (60 steps per second) is a
+    /// reasonable default.
+    pub time_step: Duration,
+}
+impl CollisionWorldParams {
+    /// Params for a world with the given layers, where nothing collides with anything yet. Fill in
+    /// the rest with struct update syntax, e.g.
+    /// `CollisionWorldParams { layer_pairs, ..CollisionWorldParams::new(layers) }`.
+    ///
+    /// Corners aren't cut, space is partitioned into 32x32 areas, and the simulation steps 60 times
+    /// per second.
+    pub fn new(layers: LayerRegistry) -> CollisionWorldParams {
+        CollisionWorldParams {
+            layers,
+            layer_pairs: Vec::new(),
+            one_way_layer_pairs: Vec::new(),
+            yield_pairs: Vec::new(),
+            corner_tolerance: 0.,
+            partition_size: [32., 32.],
+            time_step: Duration::from_micros(16666),
+        }
+    }
 }
 #[allow(clippy::new_without_default)] // API is intentionally explicit
 impl CollisionWorld {
@@ -119,213 +415,1721 @@ impl CollisionWorld {
         if params.partition_size[0] <= 0. || params.partition_size[1] <= 0. {
             panic!("Invalid partition size: {:?}", params.partition_size)
         }
+        if params.time_step == Duration::from_millis(0) {
+            panic!("Invalid time step: {:?}", params.time_step)
+        }
+        if params.corner_tolerance < 0. {
+            panic!("Invalid corner tolerance: {}", params.corner_tolerance)
+        }
 
-        let mut group_masks = HashMap::new();
-        for group_pair in params.group_pairs.iter() {
-            let group_a = group_pair[0];
-            let group_b = group_pair[1];
-            *group_masks.entry(group_a).or_insert(group_b) |= group_b;
-            *group_masks.entry(group_b).or_insert(group_a) |= group_a;
+        let registered = params.layers.mask();
+        let all_pairs = params
+            .layer_pairs
+            .iter()
+            .chain(params.one_way_layer_pairs.iter())
+            .chain(params.yield_pairs.iter());
+        for &[layer_a, layer_b] in all_pairs {
+            if !registered.contains(layer_a | layer_b) {
+                panic!("Unregistered layer in pair: {:?}", [layer_a, layer_b])
+            }
+        }
+
+        let mut collision_masks = [LayerMask::NONE; Layer::COUNT as usize];
+        for &[layer_a, layer_b] in params.layer_pairs.iter() {
+            collision_masks[layer_a.index() as usize] |= layer_b;
+            collision_masks[layer_b.index() as usize] |= layer_a;
+        }
+        for &[layer_a, layer_b] in params.one_way_layer_pairs.iter() {
+            collision_masks[layer_a.index() as usize] |= layer_b;
+        }
+
+        let mut yield_masks = [LayerMask::NONE; Layer::COUNT as usize];
+        for &[layer_a, layer_b] in params.yield_pairs.iter() {
+            yield_masks[layer_a.index() as usize] |= layer_b;
         }
 
         CollisionWorld {
             time_counter: Duration::from_millis(0),
-            time_step: Duration::from_micros(16666), // 16.67 ms, roughly 60 fps
-            group_masks,
+            time_step: params.time_step,
+            layers: params.layers,
+            collision_masks,
+            yield_masks,
+            corner_tolerance: params.corner_tolerance,
             bodies: Pool::new(),
             partition_size: params.partition_size,
             grid: Grid::new(),
-            refresh_handles: HashSet::new(),
-            moving_handles: HashSet::new(),
+            dirty_handles: BTreeSet::new(),
+            moved_handles: BTreeSet::new(),
+            moving_handles: BTreeSet::new(),
+            contacts: BTreeMap::new(),
+            events: Vec::new(),
+            tiles: None,
+            pushables: BTreeMap::new(),
+            kinematics: BTreeMap::new(),
+            settled: Vec::new(),
+            crushed: Vec::new(),
+            grid_buffer: RefCell::new(Vec::new()),
+            ray_hits: RefCell::new(Vec::new()),
+            ray_visited: RefCell::new(HashSet::new()),
         }
     }
 
-    pub fn new_body(&mut self, group: u32, pos: P2, size: V2) -> BodyHandle {
-        self.new_moving_body(group, pos, size, V2::zero())
+    /// Returns the layers registered with this world, e.g. to look one up by name.
+    pub fn layers(&self) -> &LayerRegistry {
+        &self.layers
+    }
+
+    pub fn new_body<L: Into<LayerMask>>(&mut self, layers: L, pos: P2, size: V2) -> BodyHandle {
+        self.new_moving_body(layers, pos, size, V2::zero())
     }
 
     /// Convenience method for calling `new_body` with non-zero velocity
-    pub fn new_moving_body(&mut self, group: u32, pos: P2, size: V2, vel: V2) -> BodyHandle {
-        let body = Body {
-            group,
+    pub fn new_moving_body<L: Into<LayerMask>>(
+        &mut self,
+        layers: L,
+        pos: P2,
+        size: V2,
+        vel: V2,
+    ) -> BodyHandle {
+        self.insert_body(Body {
+            kind: BodyKind::Solid,
+            layers: layers.into(),
+            shape: Shape::Rect,
+            blocks: RectSides::ALL,
             pos,
+            prev_pos: pos,
             size,
             vel,
-        };
+            accel: V2::zero(),
+            damping: 0.,
+            friction: 0.,
+            max_speed: None,
+            user_data: 0,
+        })
+    }
+
+    /// Create a body which detects other bodies overlapping it without blocking them. See
+    /// `BodyKind::Sensor` for more details.
+    pub fn new_sensor<L: Into<LayerMask>>(&mut self, layers: L, pos: P2, size: V2) -> BodyHandle {
+        self.insert_body(Body {
+            kind: BodyKind::Sensor,
+            layers: layers.into(),
+            shape: Shape::Rect,
+            blocks: RectSides::ALL,
+            pos,
+            prev_pos: pos,
+            size,
+            vel: V2::zero(),
+            accel: V2::zero(),
+            damping: 0.,
+            friction: 0.,
+            max_speed: None,
+            user_data: 0,
+        })
+    }
+
+    /// Create a body which can be pushed around by other bodies. See `BodyKind::Pushable` for more
+    /// details.
+    pub fn new_pushable<L: Into<LayerMask>>(
+        &mut self,
+        layers: L,
+        pos: P2,
+        size: V2,
+        params: PushParams,
+    ) -> BodyHandle {
+        if params.distance <= 0. || params.speed <= 0. {
+            panic!("Invalid push params: {:?}", params)
+        }
+
+        let handle = self.insert_body(Body {
+            kind: BodyKind::Pushable,
+            layers: layers.into(),
+            shape: Shape::Rect,
+            blocks: RectSides::ALL,
+            pos,
+            prev_pos: pos,
+            size,
+            vel: V2::zero(),
+            accel: V2::zero(),
+            damping: 0.,
+            friction: 0.,
+            max_speed: None,
+            user_data: 0,
+        });
+        self.pushables.insert(
+            handle.inner_handle,
+            PushState {
+                params,
+                push_dir: None,
+                push_duration: Duration::from_millis(0),
+                target: None,
+            },
+        );
+        handle
+    }
 
+    /// Create a body which moves on its own, carrying bodies along with it. See
+    /// `BodyKind::Kinematic` for more details.
+    pub fn new_kinematic<L: Into<LayerMask>>(
+        &mut self,
+        layers: L,
+        pos: P2,
+        size: V2,
+        params: KinematicParams,
+    ) -> BodyHandle {
+        if !params.path.is_empty() && params.path_speed <= 0. {
+            panic!("Invalid kinematic params: {:?}", params)
+        }
+
+        let handle = self.insert_body(Body {
+            kind: BodyKind::Kinematic,
+            layers: layers.into(),
+            shape: Shape::Rect,
+            blocks: RectSides::ALL,
+            pos,
+            prev_pos: pos,
+            size,
+            vel: V2::zero(),
+            accel: V2::zero(),
+            damping: 0.,
+            friction: 0.,
+            max_speed: None,
+            user_data: 0,
+        });
+        self.kinematics.insert(
+            handle.inner_handle,
+            KinematicState {
+                params,
+                next_point: 0,
+            },
+        );
+        handle
+    }
+
+    fn insert_body(&mut self, body: Body) -> BodyHandle {
+        // A body in no layers, or in layers nothing knows about, would never collide with anything
+        if body.layers.is_empty() || !self.layers.mask().contains(body.layers) {
+            panic!("Invalid body layers: {:?}", body.layers)
+        }
+
+        let (pos, size) = (body.pos, body.size);
         let handle = BodyHandle {
             inner_handle: self.bodies.push(body),
         };
         self.grid
             .insert(handle.inner_handle, self.create_region(pos, size));
-
-        if !vel.is_zero() {
-            self.moving_handles.insert(handle.inner_handle);
-        }
+        self.update_moving(handle.inner_handle);
 
         handle
     }
 
+    /// Remove the body at `handle`, if it still exists. Any contacts it was part of end on the next
+    /// time step.
     pub fn remove_body(&mut self, handle: BodyHandle) {
-        self.bodies.remove(handle.inner_handle);
-        self.grid.remove(handle.inner_handle);
+        let handle = handle.inner_handle;
+        if self.bodies.remove(handle).is_none() {
+            return;
+        }
+        self.grid.remove(handle);
+        self.dirty_handles.remove(&handle);
+        self.moved_handles.remove(&handle);
+        self.moving_handles.remove(&handle);
+        self.pushables.remove(&handle);
+        self.kinematics.remove(&handle);
     }
 
     pub fn body(&self, handle: BodyHandle) -> Option<&Body> {
         self.bodies.get(handle.inner_handle)
     }
 
+    /// Turn a handle to a body in another world into a handle to the body in the same place in
+    /// this one, or `None` if there isn't one. Two worlds built the same way put their bodies in
+    /// the same places, as does restoring a snapshot, so this is how handles saved to disk (or
+    /// recorded in a `replay::Replay`) get used with a world that was loaded back in.
+    pub fn rebind(&self, handle: BodyHandle) -> Option<BodyHandle> {
+        Some(BodyHandle {
+            inner_handle: self.bodies.rebind(handle.inner_handle)?,
+        })
+    }
+
+    /// Returns the body at `handle` to be changed in any way. As the world can't tell what was
+    /// changed, it has to assume everything was, and only catches up on the next `elapse_time`;
+    /// until then, e.g. queries still find the body where it was. Prefer `set_pos`, `set_vel` and
+    /// `teleport` where possible, which take effect immediately.
     pub fn body_mut(&mut self, handle: BodyHandle) -> Option<&mut Body> {
-        self.refresh_handles.insert(handle.inner_handle);
-        self.moving_handles.remove(&handle.inner_handle);
-        self.bodies.get_mut(handle.inner_handle)
+        let body = self.bodies.get_mut(handle.inner_handle)?;
+        self.dirty_handles.insert(handle.inner_handle);
+        Some(body)
+    }
+
+    /// Move the body at `handle` straight to `pos`, without colliding with anything on the way.
+    /// When rendering with `Body::interpolated_pos`, the body is still shown moving there smoothly
+    /// over the next time step; use `teleport` to have it appear there instantly.
+    pub fn set_pos(&mut self, handle: BodyHandle, pos: P2) {
+        self.set_body_pos(handle.inner_handle, pos);
+    }
+
+    /// Move the body at `handle` straight to `pos`, like `set_pos`, but without it being
+    /// interpolated there from where it was, e.g. for warps and respawns. The body is only moved if
+    /// it would fit there without overlapping anything it collides with; returns whether it was.
+    pub fn teleport(&mut self, handle: BodyHandle, pos: P2) -> bool {
+        if !self.is_free(handle, pos) {
+            return false;
+        }
+        self.set_body_pos(handle.inner_handle, pos);
+        if let Some(body) = self.bodies.get_mut(handle.inner_handle) {
+            body.prev_pos = pos;
+        }
+        true
+    }
+
+    /// Like `teleport`, but if `pos` is blocked, the body is moved to the nearest spot no further
+    /// than `max_dist` away that it does fit into instead, e.g. so a respawning player doesn't end
+    /// up stuck in an enemy that wandered onto the spawn point. Returns where the body ended up, or
+    /// `None` if there was nowhere free close enough.
+    pub fn teleport_near(&mut self, handle: BodyHandle, pos: P2, max_dist: f32) -> Option<P2> {
+        // Free spots are found by sliding out of whatever is in the way, which may just slide into
+        // something else, so we need to give up eventually
+        const MAX_TRIES: usize = 64;
+
+        let size = self.body(handle)?.size;
+        let mut candidates = vec![pos];
+        let mut tried: Vec<P2> = Vec::new();
+        while !candidates.is_empty() && tried.len() < MAX_TRIES {
+            let (index, _) = candidates
+                .iter()
+                .map(|&candidate| (candidate - pos).len())
+                .enumerate()
+                .min_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+                .unwrap();
+            let candidate = candidates.swap_remove(index);
+            tried.push(candidate);
+
+            let blockers = self.get_blockers_at(handle.inner_handle, Rect::new(candidate, size));
+            if blockers.is_empty() {
+                self.teleport(handle, candidate);
+                return Some(candidate);
+            }
+
+            for rect_other in blockers {
+                for &next in [
+                    P2::new(rect_other.left() - size.x, candidate.y),
+                    P2::new(rect_other.right(), candidate.y),
+                    P2::new(candidate.x, rect_other.top() - size.y),
+                    P2::new(candidate.x, rect_other.bottom()),
+                ]
+                .iter()
+                {
+                    if (next - pos).len() <= max_dist
+                        && !tried.contains(&next)
+                        && !candidates.contains(&next)
+                    {
+                        candidates.push(next);
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns `true` if the body at `handle` would fit at `pos` without overlapping anything it
+    /// collides with. Obstacles which only block some sides (e.g. ledges) don't count, as bodies
+    /// are allowed to pass through them.
+    pub fn is_free(&self, handle: BodyHandle, pos: P2) -> bool {
+        match self.body(handle) {
+            Some(body) => self
+                .get_blockers_at(handle.inner_handle, Rect::new(pos, body.size))
+                .is_empty(),
+            None => false,
+        }
+    }
+
+    /// Move the body at `handle` by `delta` right away, rather than waiting for `elapse_time`,
+    /// e.g. for a dash or a grappling hook. The body collides and slides along obstacles the same
+    /// way it would if it were moving by itself, but doesn't push anything out of its way.
+    pub fn move_and_slide(&mut self, handle: BodyHandle, delta: V2) -> Option<MoveResult> {
+        let handle = handle.inner_handle;
+        let rect_t0 = Rect::from(self.bodies.get(handle)?);
+
+        let (rect_x, hit_x) = self.sweep_axis_hit(handle, rect_t0, V2::new(delta.x, 0.));
+        let (rect_y, hit_y) = self.sweep_axis_hit(handle, rect_x, V2::new(0., delta.y));
+        let rect_t1 = self.nudge_around_corner(handle, rect_y, delta);
+        let rect_t1 = self.separate(handle, rect_t0, rect_t1);
+        self.set_body_pos(handle, rect_t1.pos);
+
+        let contacts = [(hit_x, rect_x), (hit_y, rect_y)]
+            .iter()
+            .filter_map(|&(hit, rect)| hit.map(|(rect_other, blocker)| (rect_other, blocker, rect)))
+            .map(|(rect_other, blocker, rect)| {
+                let side = rect_other.closest_side(&rect);
+                MoveContact {
+                    body: blocker.map(|inner_handle| BodyHandle { inner_handle }),
+                    side,
+                    normal: side.normal(),
+                }
+            })
+            .collect();
+        Some(MoveResult {
+            pos: rect_t1.pos,
+            contacts,
+        })
+    }
+
+    pub fn set_vel(&mut self, handle: BodyHandle, vel: V2) {
+        if let Some(body) = self.bodies.get_mut(handle.inner_handle) {
+            body.vel = vel;
+            self.update_moving(handle.inner_handle);
+        }
+    }
+
+    /// Instantly change the velocity of the body at `handle` by `impulse`, e.g. to knock it back
+    /// after being hit. Combined with `Body::damping` or `Body::friction`, it then slows back down
+    /// on its own, colliding with everything along the way as usual.
+    pub fn apply_impulse(&mut self, handle: BodyHandle, impulse: V2) {
+        if let Some(body) = self.bodies.get_mut(handle.inner_handle) {
+            body.vel += impulse;
+            self.update_moving(handle.inner_handle);
+        }
     }
 
     pub fn bodies(&self) -> impl Iterator<Item = &Body> {
         self.bodies.iter()
     }
 
+    /// Give back memory left over from removed bodies, e.g. after unloading a level. Handles to
+    /// bodies which still exist stay valid.
+    pub fn shrink_to_fit(&mut self) {
+        self.bodies.shrink_to_fit();
+        self.grid_buffer.get_mut().shrink_to_fit();
+        self.ray_hits.get_mut().shrink_to_fit();
+        self.ray_visited.get_mut().shrink_to_fit();
+    }
+
+    /// Like `bodies`, but along with the handle to each body.
+    pub fn bodies_with_handles(&self) -> impl Iterator<Item = (BodyHandle, &Body)> {
+        self.bodies
+            .iter_with_handles()
+            .map(|(inner_handle, body)| (BodyHandle { inner_handle }, body))
+    }
+
+    /// Returns every body to be changed in any way. See `body_mut`.
     pub fn bodies_mut(&mut self) -> impl Iterator<Item = &mut Body> {
-        self.bodies.handles().for_each(|h| {
-            self.refresh_handles.insert(h);
-        });
-        self.moving_handles.clear();
+        self.dirty_handles
+            .extend(self.bodies.iter_with_handles().map(|(handle, _)| handle));
         self.bodies.iter_mut()
     }
 
-    pub fn get_touching(&self, handle: BodyHandle) -> Vec<&Body> {
-        let mut touching = Vec::with_capacity(0); // Don't allocate by default
+    /// Set a layer of static tiles for bodies to collide with, replacing any previous one. Tiles
+    /// are much cheaper than bodies, so prefer them for level geometry like walls.
+    pub fn set_tiles(&mut self, tiles: TileLayer) {
+        if !self.layers.mask().contains(tiles.layer()) {
+            panic!("Invalid tile layer: {:?}", tiles.layer())
+        }
+        self.tiles = Some(tiles);
+    }
 
-        if let Some(body) = self.body(handle) {
-            let rect_body = Rect::from(body);
-            for other_body in self.bodies() {
-                if !other_body.vel.is_zero() || body == other_body {
-                    continue; // Only collide moving with non-moving bodies (for now...)
+    pub fn tiles(&self) -> Option<&TileLayer> {
+        self.tiles.as_ref()
+    }
+
+    /// Change the flags of a single tile in the current tile layer. See `TileLayer::set`.
+    pub fn set_tile(&mut self, x: u16, y: u16, flags: TileFlags) {
+        match self.tiles.as_mut() {
+            Some(tiles) => tiles.set(x, y, flags),
+            None => panic!("No tile layer to set a tile in"),
+        }
+    }
+
+    /// Return all solid bodies touching (or overlapping) the body at `handle`, along with their
+    /// handles. Sensors are never included, since they don't take up any space; use
+    /// `get_overlapping` for those.
+    pub fn get_touching(&self, handle: BodyHandle) -> Vec<(BodyHandle, &Body)> {
+        match self.body(handle) {
+            Some(body) => {
+                let rect_body = Rect::from(body);
+                self.get_region_bodies(self.create_region(body.pos, body.size), handle.inner_handle)
+                    .into_iter()
+                    .filter(|(_, other_body)| other_body.kind != BodyKind::Sensor)
+                    .filter(|(_, other_body)| {
+                        body.shape
+                            .touches(&rect_body, other_body.shape, &Rect::from(*other_body))
+                    })
+                    .map(|(h, other_body)| (BodyHandle { inner_handle: h }, other_body))
+                    .collect()
+            }
+            None => Vec::with_capacity(0), // Don't allocate by default
+        }
+    }
+
+    /// Return all bodies, including sensors, which overlap the body at `handle`, along with their
+    /// handles. Unlike `get_touching`, bodies which are only flush against it aren't included.
+    pub fn get_overlapping(&self, handle: BodyHandle) -> Vec<(BodyHandle, &Body)> {
+        match self.body(handle) {
+            Some(body) => {
+                let rect_body = Rect::from(body);
+                self.get_region_bodies(self.create_region(body.pos, body.size), handle.inner_handle)
+                    .into_iter()
+                    .filter(|(_, other_body)| {
+                        body.shape
+                            .overlaps(&rect_body, other_body.shape, &Rect::from(*other_body))
+                    })
+                    .map(|(h, other_body)| (BodyHandle { inner_handle: h }, other_body))
+                    .collect()
+            }
+            None => Vec::with_capacity(0),
+        }
+    }
+
+    /// Return handles to all bodies overlapping `rect` which belong to any of the layers in
+    /// `layer_mask`.
+    pub fn query_rect<L: Into<LayerMask>>(&self, rect: Rect, layer_mask: L) -> Vec<BodyHandle> {
+        let layer_mask = layer_mask.into();
+        self.query_grid(self.create_region(rect.pos, rect.size))
+            .iter()
+            .filter_map(|&h| self.bodies.get(h).map(|body| (h, body)))
+            .filter(|(_, body)| body.layers.intersects(layer_mask))
+            .filter(|(_, body)| Shape::Rect.overlaps(&rect, body.shape, &Rect::from(*body)))
+            .map(|(h, _)| BodyHandle { inner_handle: h })
+            .collect()
+    }
+
+    /// Return handles to all bodies containing the point `pt`.
+    pub fn query_point(&self, pt: P2) -> Vec<BodyHandle> {
+        self.query_grid(self.create_region(pt, V2::zero()))
+            .iter()
+            .filter_map(|&h| self.bodies.get(h).map(|body| (h, body)))
+            .filter(|(_, body)| body.shape.contains(&Rect::from(*body), pt))
+            .map(|(h, _)| BodyHandle { inner_handle: h })
+            .collect()
+    }
+
+    /// Cast `ray` out into the world, up to a distance of `max_dist`, and return the first body it
+    /// hits which belongs to any of the layers in `layer_mask`. Sensors are ignored, as they don't
+    /// take up any space.
+    ///
+    /// If the tile layer's layer is in `layer_mask`, solid tiles will stop the ray as well. Other
+    /// tiles (e.g. water) don't block anything a ray would care about.
+    pub fn raycast<L: Into<LayerMask>>(
+        &self,
+        ray: Ray,
+        max_dist: f32,
+        layer_mask: L,
+    ) -> Option<RayHit> {
+        self.cast_ray(ray, max_dist, layer_mask.into(), true)
+            .first()
+            .copied()
+    }
+
+    /// Like `raycast`, but return every body hit along the way instead of just the first one,
+    /// sorted from nearest to furthest.
+    pub fn raycast_all<L: Into<LayerMask>>(
+        &self,
+        ray: Ray,
+        max_dist: f32,
+        layer_mask: L,
+    ) -> Vec<RayHit> {
+        self.cast_ray(ray, max_dist, layer_mask.into(), false)
+            .clone()
+    }
+
+    /// Returns `true` if nothing in any of the layers in `layer_mask` blocks the straight line
+    /// between `from` and `to`.
+    pub fn line_of_sight<L: Into<LayerMask>>(&self, from: P2, to: P2, layer_mask: L) -> bool {
+        let dist = (to - from).len();
+        match self.raycast(Ray::new(from, to - from), dist, layer_mask) {
+            Some(hit) => hit.distance >= dist,
+            None => true,
+        }
+    }
+
+    /// Walk through the grid, cell by cell, along `ray`, collecting every body it hits.
+    ///
+    /// This uses a DDA approach, so we only check bodies which live in cells the ray actually
+    /// passes through. If `nearest_only` is true, we can stop the moment we've found a hit closer
+    /// than anything a later cell could possibly contain.
+    ///
+    /// The hits are returned in a buffer that's reused by every call, so they have to be copied
+    /// out (or dropped) before casting another ray.
+    fn cast_ray(
+        &self,
+        ray: Ray,
+        max_dist: f32,
+        layer_mask: LayerMask,
+        nearest_only: bool,
+    ) -> RefMut<'_, Vec<RayHit>> {
+        let mut hits = self.ray_hits.borrow_mut();
+        let mut visited = self.ray_visited.borrow_mut();
+        hits.clear();
+        visited.clear();
+
+        let cell_size = V2::from(self.partition_size);
+        let cell_origin = ray.origin / self.partition_size;
+        let mut cell = [cell_origin.x.floor() as i32, cell_origin.y.floor() as i32];
+        let mut cell_step = [0, 0];
+        // How far along the ray we have to go to cross into the next cell, for each axis
+        let mut dist_next = [f32::INFINITY, f32::INFINITY];
+        // How far along the ray we have to go to cross an entire cell, for each axis
+        let mut dist_delta = [f32::INFINITY, f32::INFINITY];
+        for axis in 0..2 {
+            let (origin, dir, size) = if axis == 0 {
+                (ray.origin.x, ray.dir.x, cell_size.x)
+            } else {
+                (ray.origin.y, ray.dir.y, cell_size.y)
+            };
+
+            if dir > 0. {
+                cell_step[axis] = 1;
+                dist_next[axis] = ((cell[axis] + 1) as f32 * size - origin) / dir;
+                dist_delta[axis] = size / dir;
+            } else if dir < 0. {
+                cell_step[axis] = -1;
+                dist_next[axis] = (cell[axis] as f32 * size - origin) / dir;
+                dist_delta[axis] = size / -dir;
+            }
+        }
+
+        let i16_range = i32::from(i16::MIN)..=i32::from(i16::MAX);
+        while i16_range.contains(&cell[0]) && i16_range.contains(&cell[1]) {
+            let region = GridRegion::square(cell[0] as i16, cell[1] as i16);
+            for &handle in self.query_grid(region).iter() {
+                if !visited.insert(handle) {
+                    continue;
                 }
 
-                let rect_other = Rect::from(other_body);
-                if rect_body.touches(&rect_other) {
-                    touching.push(other_body);
+                let body = self.bodies.get(handle).unwrap();
+                if !body.layers.intersects(layer_mask) || body.kind == BodyKind::Sensor {
+                    continue;
+                }
+                if let Some(intersection) = ray.intersect_shape(body.shape, &Rect::from(body)) {
+                    if intersection.distance <= max_dist {
+                        hits.push(RayHit {
+                            body: Some(BodyHandle {
+                                inner_handle: handle,
+                            }),
+                            point: intersection.point,
+                            normal: intersection.normal,
+                            distance: intersection.distance,
+                        });
+                    }
                 }
             }
+
+            let dist_cell_exit = dist_next[0].min(dist_next[1]);
+            if dist_cell_exit > max_dist || dist_cell_exit.is_infinite() {
+                break;
+            }
+            if nearest_only && hits.iter().any(|hit| hit.distance <= dist_cell_exit) {
+                break;
+            }
+
+            let axis = if dist_next[0] < dist_next[1] { 0 } else { 1 };
+            cell[axis] += cell_step[axis];
+            dist_next[axis] += dist_delta[axis];
         }
 
-        touching
+        if let Some(tiles) = self
+            .tiles
+            .as_ref()
+            .filter(|t| layer_mask.intersects(t.layer()))
+        {
+            // Unlike bodies, there's no way to see past a tile, so only the first one matters
+            if let Some(intersection) = tiles.raycast(ray, max_dist, TileFlags::SOLID) {
+                hits.retain(|hit| hit.distance <= intersection.distance);
+                hits.push(RayHit {
+                    body: None,
+                    point: intersection.point,
+                    normal: intersection.normal,
+                    distance: intersection.distance,
+                });
+            }
+        }
+
+        hits.sort_by(|a, b| a.distance.partial_cmp(&b.distance).unwrap());
+        if nearest_only {
+            hits.truncate(1);
+        }
+        hits
     }
 
-    fn get_region_bodies(&self, region: GridRegion, exclude: PoolHandle) -> Vec<&Body> {
-        self.grid
-            .query(region)
+    fn get_region_bodies(
+        &self,
+        region: GridRegion,
+        exclude: PoolHandle,
+    ) -> Vec<(PoolHandle, &Body)> {
+        self.query_grid(region)
             .iter()
-            .filter(|&&h| *h != exclude)
-            .filter_map(|&h| self.bodies.get(*h))
+            .filter(|&&h| h != exclude)
+            .filter_map(|&h| self.bodies.get(h).map(|body| (h, body)))
             .collect()
     }
 
-    pub fn elapse_time(&mut self, duration: Duration) {
-        if !self.refresh_handles.is_empty() {
-            for refresh_handle in self.refresh_handles.iter() {
-                if let Some(body) = self.bodies.get(*refresh_handle) {
-                    self.grid
-                        .insert(*refresh_handle, self.create_region(body.pos, body.size));
-
-                    if !body.vel.is_zero() {
-                        self.moving_handles.insert(*refresh_handle);
-                    }
+    /// Return handles to every body in `region`, sorted so that the order bodies are processed in
+    /// (and so the outcome of the simulation) is always the same for the same world.
+    ///
+    /// Like `cast_ray`, the handles are returned in a buffer that's reused by every call, so they
+    /// have to be dropped before querying again.
+    fn query_grid(&self, region: GridRegion) -> RefMut<'_, Vec<PoolHandle>> {
+        let mut handles = self.grid_buffer.borrow_mut();
+        self.grid.query_into(region, &mut handles);
+        handles
+    }
+
+    /// Return all contacts reported by the last call to `elapse_time`, clearing them in the
+    /// process.
+    ///
+    /// Every time step, a moving body touching another body it can collide with will report a
+    /// `ContactKind::Began` event followed by a `ContactKind::Persisted` event every step after
+    /// that, until finally a `ContactKind::Ended` event when they separate. Each pair of bodies is
+    /// only reported once per step, even if both of them are moving.
+    ///
+    /// Events are collected over a single call to `elapse_time` (which may run several time
+    /// steps); any events not drained before the next call are discarded.
+    pub fn drain_events(&mut self) -> impl Iterator<Item = ContactEvent> + '_ {
+        self.events.drain(..)
+    }
+
+    /// Return all pushable bodies (see `BodyKind::Pushable`) which finished moving into place
+    /// during the last call to `elapse_time`, clearing them in the process. Like `drain_events`,
+    /// any not drained before the next call are discarded.
+    pub fn drain_settled(&mut self) -> impl Iterator<Item = BodyHandle> + '_ {
+        self.settled.drain(..)
+    }
+
+    /// Return all bodies which got caught between a kinematic body (see `BodyKind::Kinematic`)
+    /// and something else during the last call to `elapse_time`, with no room to be squeezed out
+    /// sideways, e.g. so a player caught between a platform and a wall can be killed. Each one
+    /// was moved to the nearest free spot next to the kinematic body instead, if there was one.
+    /// Like `drain_events`, any not drained before the next call are discarded.
+    pub fn drain_crushed(&mut self) -> impl Iterator<Item = BodyHandle> + '_ {
+        self.crushed.drain(..)
+    }
+
+    /// How far the world is between its last time step and its next one, from 0 up to (but not
+    /// including) 1. Time passed into `elapse_time` only moves bodies once it adds up to a whole
+    /// time step, so rendering bodies at `Body::interpolated_pos` with this avoids stuttering when
+    /// the frame rate and the step rate don't line up.
+    pub fn interpolation_alpha(&self) -> f32 {
+        duration_secs(self.time_counter) / duration_secs(self.time_step)
+    }
+
+    /// Capture everything about the world which changes as it runs, so it can be put back later
+    /// with `restore`.
+    pub fn snapshot(&self) -> WorldSnapshot {
+        WorldSnapshot {
+            partition_size: self.partition_size,
+            time_step: self.time_step,
+            time_counter: self.time_counter,
+            bodies: self.bodies.clone(),
+            grid: self.grid.clone(),
+            dirty_handles: self.dirty_handles.clone(),
+            moved_handles: self.moved_handles.clone(),
+            moving_handles: self.moving_handles.clone(),
+            contacts: self.contacts.clone(),
+            events: self.events.clone(),
+            tiles: self.tiles.clone(),
+            pushables: self.pushables.clone(),
+            kinematics: self.kinematics.clone(),
+            settled: self.settled.clone(),
+            crushed: self.crushed.clone(),
+        }
+    }
+
+    /// Put the world back exactly how it was when `snapshot` was taken. Handles to bodies which
+    /// existed at the time are valid again, while handles to bodies created since are not. The
+    /// world keeps its own handles though, so restoring a snapshot taken from another world (or
+    /// loaded from disk) doesn't make that world's handles valid here.
+    ///
+    /// The snapshot must come from a world created with the same params as this one.
+    pub fn restore(&mut self, snapshot: &WorldSnapshot) {
+        if snapshot.partition_size != self.partition_size || snapshot.time_step != self.time_step {
+            panic!(
+                "Snapshot doesn't match world: partition size {:?} and time step {:?}",
+                snapshot.partition_size, snapshot.time_step
+            )
+        }
+
+        // The snapshot's handles may come from another pool (or none at all, if it was
+        // deserialized), but the world keeps its own, so handles given out before stay valid
+        let snapshot = snapshot.clone();
+        let mut bodies = snapshot.bodies;
+        bodies.take_id(&self.bodies);
+        let rebind = |handle: PoolHandle| bodies.adopt(handle);
+        let rebind_body = |handle: BodyHandle| BodyHandle {
+            inner_handle: rebind(handle.inner_handle),
+        };
+
+        let mut grid = Grid::new();
+        for (handle, region) in snapshot.grid.regions() {
+            grid.insert(rebind(*handle), *region);
+        }
+        self.grid = grid;
+        self.time_counter = snapshot.time_counter;
+        self.dirty_handles = snapshot.dirty_handles.into_iter().map(rebind).collect();
+        self.moved_handles = snapshot.moved_handles.into_iter().map(rebind).collect();
+        self.moving_handles = snapshot.moving_handles.into_iter().map(rebind).collect();
+        self.contacts = snapshot
+            .contacts
+            .into_iter()
+            .map(|((handle, other), side)| ((rebind(handle), rebind(other)), side))
+            .collect();
+        self.events = snapshot
+            .events
+            .into_iter()
+            .map(|event| ContactEvent {
+                body: rebind_body(event.body),
+                other: rebind_body(event.other),
+                ..event
+            })
+            .collect();
+        self.tiles = snapshot.tiles;
+        self.pushables = snapshot
+            .pushables
+            .into_iter()
+            .map(|(handle, state)| (rebind(handle), state))
+            .collect();
+        self.kinematics = snapshot
+            .kinematics
+            .into_iter()
+            .map(|(handle, state)| (rebind(handle), state))
+            .collect();
+        self.settled = snapshot.settled.into_iter().map(rebind_body).collect();
+        self.crushed = snapshot.crushed.into_iter().map(rebind_body).collect();
+        self.bodies = bodies;
+    }
+
+    /// Returns a hash of everything about the world which changes as it runs. Bodies are always
+    /// resolved in the same order, so two worlds set up the same way and fed the same inputs end
+    /// up with the same hash, making this a cheap way to check that a `replay::Replay` is still in
+    /// sync (or that a snapshot restored correctly).
+    pub fn state_hash(&self) -> u64 {
+        let mut hasher = StateHasher::new();
+        self.time_counter.hash(&mut hasher);
+        for (handle, body) in self.bodies.iter_with_handles() {
+            handle.hash(&mut hasher);
+            body.kind.hash(&mut hasher);
+            body.layers.hash(&mut hasher);
+            body.shape.hash(&mut hasher);
+            body.blocks.hash(&mut hasher);
+            body.user_data.hash(&mut hasher);
+            hash_f32s(
+                &[
+                    body.pos.x,
+                    body.pos.y,
+                    body.prev_pos.x,
+                    body.prev_pos.y,
+                    body.size.x,
+                    body.size.y,
+                    body.vel.x,
+                    body.vel.y,
+                    body.accel.x,
+                    body.accel.y,
+                    body.damping,
+                    body.friction,
+                    body.max_speed.unwrap_or(-1.),
+                ],
+                &mut hasher,
+            );
+        }
+        self.dirty_handles.hash(&mut hasher);
+        self.moving_handles.hash(&mut hasher);
+        self.contacts.hash(&mut hasher);
+        for (handle, state) in self.pushables.iter() {
+            handle.hash(&mut hasher);
+            state.push_duration.hash(&mut hasher);
+            let push_dir = state.push_dir.unwrap_or_else(V2::zero);
+            let target = state.target.unwrap_or_else(|| P2::new(f32::NAN, f32::NAN));
+            hash_f32s(&[push_dir.x, push_dir.y, target.x, target.y], &mut hasher);
+        }
+        for (handle, state) in self.kinematics.iter() {
+            handle.hash(&mut hasher);
+            state.next_point.hash(&mut hasher);
+        }
+        if let Some(tiles) = &self.tiles {
+            let (num_tiles_x, num_tiles_y) = tiles.num_tiles();
+            for y in 0..num_tiles_y {
+                for x in 0..num_tiles_x {
+                    tiles.get(x, y).hash(&mut hasher);
                 }
             }
-            self.refresh_handles.clear();
+        }
+        hasher.finish()
+    }
+
+    pub fn elapse_time(&mut self, duration: Duration) {
+        self.events.clear();
+        self.settled.clear();
+        self.crushed.clear();
+
+        for handle in mem::take(&mut self.dirty_handles) {
+            if let Some(body) = self.bodies.get(handle) {
+                self.grid
+                    .insert(handle, self.create_region(body.pos, body.size));
+                self.moved_handles.insert(handle);
+                self.update_moving(handle);
+            }
         }
 
         self.time_counter += duration;
-        let time_step_secs =
-            self.time_step.as_secs() as f32 + self.time_step.subsec_micros() as f32 / 1_000_000f32;
+        let time_step_secs = duration_secs(self.time_step);
         while self.time_counter >= self.time_step {
             self.time_counter -= self.time_step;
+            self.step(time_step_secs);
+        }
+    }
+
+    /// Move every moving body forward by a single time step.
+    ///
+    /// Moving bodies take turns, and as soon as one has moved, its new position is what everyone
+    /// else will run into. This means order matters: if one body is chasing another, and the
+    /// chaser goes first, it will bump into where its target *used* to be and fall behind. To
+    /// avoid this, a body that would run into another moving body which hasn't had its turn yet
+    /// waits until after that body has moved.
+    fn step(&mut self, time_step_secs: f32) {
+        for handle in mem::take(&mut self.moved_handles) {
+            if let Some(body) = self.bodies.get_mut(handle) {
+                body.prev_pos = body.pos;
+            }
+        }
+
+        self.update_velocities(time_step_secs);
+        self.update_kinematics(time_step_secs);
+
+        // Kinematic bodies have already moved, and nothing can get in their way anyway
+        let mut waiting: VecDeque<PoolHandle> = self
+            .moving_handles
+            .iter()
+            .filter(|handle| !self.kinematics.contains_key(handle))
+            .cloned()
+            .collect();
+        // How many times in a row bodies have chosen to wait. If everyone remaining is waiting on
+        // everyone else (e.g. two bodies walking into each other head-on), someone has to go.
+        let mut num_stalled = 0;
+        // Pushable bodies being pushed this step, and the direction they're being pushed in
+        let mut pushes: HashMap<PoolHandle, V2> = HashMap::new();
+
+        while let Some(moving_handle) = waiting.pop_front() {
+            let (rect_t0, vel_step) = match self.bodies.get(moving_handle) {
+                Some(moving_body) => (Rect::from(moving_body), moving_body.vel * time_step_secs),
+                None => continue,
+            };
+
+            // In more complex collision systems, you need to handle arbitrary shapes bumping
+            // against other arbitrary shapes at any angle. In our case, however, we only have
+            // rectangles moving on a grid. This lets us simplify our collision logic immensely (as
+            // long as our requirements don't change in the future...)
+            //
+            // In order to avoid bodies getting stuck on corners (and other tricky things which can
+            // happen when a body is moving at an angle), we break down each body's movement into
+            // x- and y- components, and resolve collisions in two passes.
+            let (rect_x, blocker_x) =
+                self.sweep_axis(moving_handle, rect_t0, V2::new(vel_step.x, 0.));
+            let (rect_y, blocker_y) =
+                self.sweep_axis(moving_handle, rect_x, V2::new(0., vel_step.y));
 
+            if num_stalled <= waiting.len() {
+                let is_blocked_by_waiting = blocker_x
+                    .into_iter()
+                    .chain(blocker_y)
+                    .any(|blocker| waiting.contains(&blocker));
+                if is_blocked_by_waiting {
+                    waiting.push_back(moving_handle);
+                    num_stalled += 1;
+                    continue;
+                }
+            }
+            num_stalled = 0;
+
+            for (blocker, dir) in [
+                (blocker_x, V2::new(vel_step.x.signum(), 0.)),
+                (blocker_y, V2::new(0., vel_step.y.signum())),
+            ]
+            .iter()
             {
-                for moving_handle in self.moving_handles.iter() {
-                    let moving_body = self.bodies.get(*moving_handle).unwrap();
-                    let group_masks = *self.group_masks.get(&moving_body.group).unwrap_or(&0);
-                    let rect_t0 = Rect::from(moving_body);
-                    let vel_step = moving_body.vel * time_step_secs;
-                    let mut rect_t1 = rect_t0;
-
-                    // In more complex collision systems, you need to handle arbitrary shapes
-                    // bumping against other arbitrary shapes at any angle. In our case, however,
-                    // we only have rectangles moving on a grid. This lets us simplify our collision
-                    // logic immensely (as long as our requirements don't change in the future...)
-                    //
-                    // In order to avoid bodies getting stuck on corners (and other tricky things
-                    // which can happen when a body is moving at an angle), we break down each
-                    // body's movement into x- and y- components, and resolve collisions in two
-                    // passes.
-
-                    let dynamic_region = GridRegion::bounding(
-                        self.create_region(rect_t0.pos, rect_t0.size),
-                        self.create_region(rect_t0.pos + vel_step, rect_t1.size),
-                    );
-                    let nearby_bodies = self.get_region_bodies(dynamic_region, *moving_handle);
-
-                    // If we are trying to move horizontally, check if we collide first
-                    if vel_step.x != 0. {
-                        rect_t1.pos.x = rect_t0.pos.x + vel_step.x;
-                        for nearby_body in &nearby_bodies {
-                            if nearby_body.group & group_masks == 0 {
-                                continue;
-                            }
-                            let rect_curr = Rect::from(*nearby_body);
-
-                            if rect_curr.overlaps(&rect_t1) {
-                                match rect_curr.collided_side(&rect_t0, &rect_t1) {
-                                    RectSide::Left => {
-                                        rect_t1.pos.x = rect_curr.left() - rect_t1.size.x
-                                    }
-                                    RectSide::Right => rect_t1.pos.x = rect_curr.right(),
-                                    _ => {}
-                                }
-                            }
-                        }
-                    }
+                if let Some(blocker) = blocker.filter(|b| self.pushables.contains_key(b)) {
+                    pushes.insert(blocker, *dir);
+                }
+            }
 
-                    // If we are trying to move vertically, check if we collide first
-                    if vel_step.y != 0. {
-                        rect_t1.pos.y = rect_t0.pos.y + vel_step.y;
-                        for nearby_body in &nearby_bodies {
-                            if nearby_body.group & group_masks == 0 {
-                                continue;
-                            }
-
-                            let rect_curr = Rect::from(*nearby_body);
-
-                            if rect_curr.overlaps(&rect_t1) {
-                                match rect_curr.collided_side(&rect_t0, &rect_t1) {
-                                    RectSide::Top => {
-                                        rect_t1.pos.y = rect_curr.top() - rect_t1.size.y
-                                    }
-                                    RectSide::Bottom => rect_t1.pos.y = rect_curr.bottom(),
-                                    _ => {}
-                                }
-                            }
-                        }
+            let needs_push = blocker_x
+                .into_iter()
+                .chain(blocker_y)
+                .any(|blocker| self.yields_to(blocker, moving_handle));
+            let rect_t1 = if needs_push {
+                let rect_x = self.push_axis(moving_handle, rect_t0, V2::new(vel_step.x, 0.), 0);
+                self.push_axis(moving_handle, rect_x, V2::new(0., vel_step.y), 0)
+            } else {
+                rect_y
+            };
+            let rect_t1 = self.nudge_around_corner(moving_handle, rect_t1, vel_step);
+            let rect_t1 = self.separate(moving_handle, rect_t0, rect_t1);
+            self.set_body_pos(moving_handle, rect_t1.pos);
+        }
+
+        self.update_pushables(&pushes, time_step_secs);
+        self.update_contacts();
+    }
+
+    /// Accelerate and slow down every moving body over a single time step, before any of them
+    /// move. Bodies which come to a complete stop are no longer considered moving.
+    fn update_velocities(&mut self, time_step_secs: f32) {
+        let bodies = &mut self.bodies;
+        self.moving_handles
+            .retain(|&handle| match bodies.get_mut(handle) {
+                Some(body) => {
+                    body.integrate_vel(time_step_secs);
+                    !body.vel.is_zero() || !body.accel.is_zero()
+                }
+                None => false,
+            });
+    }
+
+    /// Move every kinematic body forward by a single time step, carrying its riders along and
+    /// pushing anything in its way aside. Kinematic bodies go before everyone else, so bodies
+    /// moving under their own power run into platforms where they are now, not where they were.
+    fn update_kinematics(&mut self, time_step_secs: f32) {
+        let handles: Vec<PoolHandle> = self.kinematics.keys().cloned().collect();
+        for handle in handles {
+            let (rect_t0, vel, layers, shape) = match self.bodies.get(handle) {
+                Some(body) => (Rect::from(body), body.vel, body.layers, body.shape),
+                None => continue,
+            };
+            let state = self.kinematics.get_mut(&handle).unwrap();
+            let delta = state.advance(rect_t0.pos, vel, time_step_secs);
+            let (carries, surface_vel) = (state.params.carries, state.params.surface_vel);
+
+            let riders: Vec<PoolHandle> = self
+                .get_region_bodies(self.create_region(rect_t0.pos, rect_t0.size), handle)
+                .into_iter()
+                .filter(|(_, body)| {
+                    body.layers.intersects(carries)
+                        && body.kind != BodyKind::Sensor
+                        && body.kind != BodyKind::Kinematic
+                        && rect_t0.contains(body.pos + body.size * 0.5)
+                })
+                .map(|(rider, _)| rider)
+                .collect();
+
+            let rect_t1 = Rect::new(rect_t0.pos + delta, rect_t0.size);
+            if !delta.is_zero() {
+                self.set_body_pos(handle, rect_t1.pos);
+            }
+
+            let carry_delta = delta + surface_vel * time_step_secs;
+            if !carry_delta.is_zero() {
+                for &rider in riders.iter() {
+                    self.shove_body(rider, carry_delta);
+                }
+            }
+
+            // Anything we've moved into gets shoved back out of our way
+            let collision_mask = self.collision_mask(layers);
+            let region = self.create_region(rect_t1.pos, rect_t1.size);
+            let pushes: Vec<(PoolHandle, V2)> = self
+                .get_region_bodies(region, handle)
+                .into_iter()
+                .filter(|(other, other_body)| {
+                    !riders.contains(other)
+                        && other_body.kind != BodyKind::Sensor
+                        && other_body.kind != BodyKind::Kinematic
+                        && (other_body.layers.intersects(collision_mask)
+                            || layers.intersects(self.collision_mask(other_body.layers)))
+                })
+                .filter_map(|(other, other_body)| {
+                    other_body
+                        .shape
+                        .penetration(&Rect::from(other_body), shape, &rect_t1)
+                        .map(|push| (other, push))
+                })
+                .collect();
+            for (other, push) in pushes {
+                self.shove_body(other, push);
+                if self.overlaps_shape(other, shape, &rect_t1) {
+                    self.squeeze_body(other, shape, &rect_t1, delta);
+                }
+            }
+        }
+    }
+
+    /// Returns `true` if the body at `handle` overlaps `shape`, fit inside `rect`.
+    fn overlaps_shape(&self, handle: PoolHandle, shape: Shape, rect: &Rect) -> bool {
+        match self.bodies.get(handle) {
+            Some(body) => body.shape.overlaps(&Rect::from(body), shape, rect),
+            None => false,
+        }
+    }
+
+    /// Get the body at `handle` out of a kinematic body, of `shape` and fit inside `rect`, which
+    /// moved by `delta` into it but couldn't push it out of the way, as something is in the way.
+    /// The body is squeezed out sideways if possible, whichever way is shortest, and otherwise
+    /// it's crushed (see `drain_crushed`).
+    fn squeeze_body(&mut self, handle: PoolHandle, shape: Shape, rect: &Rect, delta: V2) {
+        let (rect_t0, body_shape) = match self.bodies.get(handle) {
+            Some(body) => (Rect::from(body), body.shape),
+            None => return,
+        };
+
+        let mut escapes = if delta.x.abs() >= delta.y.abs() {
+            [
+                V2::new(0., rect.top() - rect_t0.bottom()),
+                V2::new(0., rect.bottom() - rect_t0.top()),
+            ]
+        } else {
+            [
+                V2::new(rect.left() - rect_t0.right(), 0.),
+                V2::new(rect.right() - rect_t0.left(), 0.),
+            ]
+        };
+        escapes.sort_by(|a, b| a.len().partial_cmp(&b.len()).unwrap());
+        for &escape in escapes.iter() {
+            self.shove_body(handle, escape);
+            if !self.overlaps_shape(handle, shape, rect) {
+                return;
+            }
+            self.set_body_pos(handle, rect_t0.pos);
+        }
+
+        self.crushed.push(BodyHandle {
+            inner_handle: handle,
+        });
+        let size = rect_t0.size;
+        let mut spots = [
+            P2::new(rect.left() - size.x, rect_t0.pos.y),
+            P2::new(rect.right(), rect_t0.pos.y),
+            P2::new(rect_t0.pos.x, rect.top() - size.y),
+            P2::new(rect_t0.pos.x, rect.bottom()),
+        ];
+        spots.sort_by(|a, b| {
+            let (dist_a, dist_b) = ((*a - rect_t0.pos).len(), (*b - rect_t0.pos).len());
+            dist_a.partial_cmp(&dist_b).unwrap()
+        });
+        for &spot in spots.iter() {
+            let rect_spot = Rect::new(spot, size);
+            if self.get_blockers_at(handle, rect_spot).is_empty()
+                && !body_shape.overlaps(&rect_spot, shape, rect)
+            {
+                self.set_body_pos(handle, spot);
+                return;
+            }
+        }
+    }
+
+    /// Move a body by `delta`, which isn't its own doing (e.g. it's being carried), making sure
+    /// it's still blocked by everything it would normally be blocked by.
+    fn shove_body(&mut self, handle: PoolHandle, delta: V2) {
+        let rect_t0 = match self.bodies.get(handle) {
+            Some(body) => Rect::from(body),
+            None => return,
+        };
+        let (rect_x, _) = self.sweep_axis(handle, rect_t0, V2::new(delta.x, 0.));
+        let (rect_y, _) = self.sweep_axis(handle, rect_x, V2::new(0., delta.y));
+        let rect_t1 = self.separate(handle, rect_t0, rect_y);
+        self.set_body_pos(handle, rect_t1.pos);
+    }
+
+    /// Move any pushable bodies that were already pushed, and start moving any that have now been
+    /// pushed for long enough. `pushes` are the pushables being pushed this step, and which way.
+    fn update_pushables(&mut self, pushes: &HashMap<PoolHandle, V2>, time_step_secs: f32) {
+        let handles: Vec<PoolHandle> = self.pushables.keys().cloned().collect();
+        for handle in handles {
+            let rect = match self.bodies.get(handle) {
+                Some(body) => Rect::from(body),
+                None => continue,
+            };
+            let mut state = self.pushables[&handle];
+
+            if let Some(target) = state.target {
+                // Pushes don't count while already moving
+                let remaining = target - rect.pos;
+                let max_step = state.params.speed * time_step_secs;
+                let is_arriving = remaining.len() <= max_step;
+                let delta = if is_arriving {
+                    remaining
+                } else {
+                    remaining.normalized() * max_step
+                };
+
+                let (rect_moved, _) = self.sweep_axis(handle, rect, delta);
+                let is_blocked = rect_moved.pos != rect.pos + delta;
+                if is_arriving || is_blocked {
+                    // If something got in our way, just stop where we are
+                    state.target = None;
+                    self.settled.push(BodyHandle {
+                        inner_handle: handle,
+                    });
+                }
+                let pos = if is_arriving && !is_blocked {
+                    target // Avoid any floating point drift
+                } else {
+                    rect_moved.pos
+                };
+                self.set_body_pos(handle, pos);
+            } else if let Some(&dir) = pushes.get(&handle) {
+                if state.push_dir != Some(dir) {
+                    state.push_dir = Some(dir);
+                    state.push_duration = Duration::from_millis(0);
+                }
+                state.push_duration += self.time_step;
+
+                if state.push_duration >= state.params.delay {
+                    state.push_dir = None;
+                    // Snap to the nearest multiple of `distance` along the pushed axis
+                    let distance = state.params.distance;
+                    let target = rect.pos + dir * distance;
+                    let target = if dir.x != 0. {
+                        P2::new((target.x / distance).round() * distance, target.y)
+                    } else {
+                        P2::new(target.x, (target.y / distance).round() * distance)
+                    };
+
+                    // Only move if there's room to move all the way there
+                    let delta = target - rect.pos;
+                    let (rect_moved, _) = self.sweep_axis(handle, rect, delta);
+                    if rect_moved.pos == rect.pos + delta {
+                        state.target = Some(target);
                     }
+                }
+            } else {
+                state.push_dir = None;
+            }
+
+            self.pushables.insert(handle, state);
+        }
+    }
 
-                    self.grid.insert(
-                        *moving_handle,
-                        self.create_region(rect_t1.pos, rect_t1.size),
-                    );
-                    self.bodies.get_mut(*moving_handle).unwrap().pos = rect_t1.pos;
+    /// Figure out which bodies are touching now that everyone has moved, comparing the results
+    /// against the previous step to produce `ContactEvent`s.
+    fn update_contacts(&mut self) {
+        let mut contacts = BTreeMap::new();
+
+        // Only moving bodies can make new contacts, but we also need to check bodies that were
+        // already touching something, in case they just stopped moving.
+        let candidates: BTreeSet<PoolHandle> = self
+            .moving_handles
+            .iter()
+            .cloned()
+            .chain(self.contacts.keys().map(|(handle, _)| *handle))
+            .chain(
+                self.pushables
+                    .iter()
+                    .filter(|(_, state)| state.target.is_some())
+                    .map(|(handle, _)| *handle),
+            )
+            .chain(self.kinematics.keys().cloned())
+            .collect();
+        for handle in candidates {
+            let body = match self.bodies.get(handle) {
+                Some(body) => body,
+                None => continue,
+            };
+            let collision_mask = self.collision_mask(body.layers);
+            let rect_body = Rect::from(body);
+            let region = self.create_region(body.pos, body.size);
+            for (other_handle, other_body) in self.get_region_bodies(region, handle) {
+                // One-way layer pairs still count as colliding, no matter which side is moving
+                let can_collide = other_body.layers.intersects(collision_mask)
+                    || body
+                        .layers
+                        .intersects(self.collision_mask(other_body.layers));
+                if !can_collide || contacts.contains_key(&(other_handle, handle)) {
+                    continue;
+                }
+
+                // Sensors don't take up any space, so it's not enough just to be flush against them
+                let rect_other = Rect::from(other_body);
+                let is_contact =
+                    if body.kind == BodyKind::Sensor || other_body.kind == BodyKind::Sensor {
+                        body.shape
+                            .overlaps(&rect_body, other_body.shape, &rect_other)
+                    } else {
+                        body.shape
+                            .touches(&rect_body, other_body.shape, &rect_other)
+                    };
+                if is_contact {
+                    contacts.insert((handle, other_handle), rect_other.closest_side(&rect_body));
                 }
             }
         }
+
+        for (&(handle, other_handle), &side) in contacts.iter() {
+            let was_touching = self.contacts.contains_key(&(handle, other_handle))
+                || self.contacts.contains_key(&(other_handle, handle));
+            let kind = if was_touching {
+                ContactKind::Persisted
+            } else {
+                ContactKind::Began
+            };
+            self.events
+                .push(ContactEvent::new(kind, handle, other_handle, side));
+        }
+        for (&(handle, other_handle), &side) in self.contacts.iter() {
+            let is_touching = contacts.contains_key(&(handle, other_handle))
+                || contacts.contains_key(&(other_handle, handle));
+            if !is_touching {
+                self.events.push(ContactEvent::new(
+                    ContactKind::Ended,
+                    handle,
+                    other_handle,
+                    side,
+                ));
+            }
+        }
+
+        self.contacts = contacts;
+    }
+
+    /// If the body at `handle`, currently occupying `rect`, is trying to move straight along one
+    /// axis by `delta` but is blocked by something it only barely overlaps (see
+    /// `CollisionWorldParams::corner_tolerance`), nudge it sideways towards the edge of that
+    /// something, as long as there's room to do so. Returns where the body would end up.
+    fn nudge_around_corner(&self, handle: PoolHandle, rect: Rect, delta: V2) -> Rect {
+        if self.corner_tolerance <= 0. || (delta.x != 0.) == (delta.y != 0.) {
+            return rect;
+        }
+        // Round bodies already slide around corners by themselves, see `separate`
+        match self.bodies.get(handle) {
+            Some(body) if body.shape == Shape::Rect => {}
+            _ => return rect,
+        }
+        let (rect_blocked, rect_blocker) = match self.sweep_axis_hit(handle, rect, delta) {
+            (rect_blocked, Some((rect_blocker, _))) => (rect_blocked, rect_blocker),
+            _ => return rect,
+        };
+
+        // How much of the blocker we overlap along the other axis, from either side
+        let (min, max, blocker_min, blocker_max) = if delta.x != 0. {
+            (
+                rect.top(),
+                rect.bottom(),
+                rect_blocker.top(),
+                rect_blocker.bottom(),
+            )
+        } else {
+            (
+                rect.left(),
+                rect.right(),
+                rect_blocker.left(),
+                rect_blocker.right(),
+            )
+        };
+        let shift = if blocker_max - min <= self.corner_tolerance {
+            blocker_max - min
+        } else if max - blocker_min <= self.corner_tolerance {
+            blocker_min - max
+        } else {
+            return rect;
+        };
+        let perpendicular = |amount: f32| {
+            if delta.x != 0. {
+                V2::new(0., amount)
+            } else {
+                V2::new(amount, 0.)
+            }
+        };
+
+        // Only worth nudging if, once we're clear of the corner, we could get further than before
+        let (rect_clear, _) = self.sweep_axis(handle, rect, perpendicular(shift));
+        if rect_clear.pos != rect.pos + perpendicular(shift) {
+            return rect;
+        }
+        let (rect_past, _) = self.sweep_axis(handle, rect_clear, delta);
+        let progress = rect_past.pos - rect_blocked.pos;
+        if progress.x * delta.x + progress.y * delta.y <= 0. {
+            return rect;
+        }
+
+        // Slide no faster than we were trying to move in the first place
+        let step = shift.signum() * shift.abs().min(delta.len());
+        self.sweep_axis(handle, rect, perpendicular(step)).0
+    }
+
+    /// Move the body at `handle`, currently occupying `rect`, by `delta` (which should only be
+    /// along a single axis), stopping it early against the first body it would otherwise run into.
+    ///
+    /// Returns where the body would end up and the handle of the body that blocked it, if any (a
+    /// body blocked by a tile won't have a handle). This method only calculates the result; it
+    /// doesn't actually move anything.
+    fn sweep_axis(&self, handle: PoolHandle, rect: Rect, delta: V2) -> (Rect, Option<PoolHandle>) {
+        let (rect_moved, hit) = self.sweep_axis_hit(handle, rect, delta);
+        (rect_moved, hit.and_then(|(_, blocker)| blocker))
+    }
+
+    /// Like `sweep_axis`, but also returns the rect of whatever blocked the body, which may be a
+    /// tile instead of a body.
+    fn sweep_axis_hit(
+        &self,
+        handle: PoolHandle,
+        rect: Rect,
+        delta: V2,
+    ) -> (Rect, Option<(Rect, Option<PoolHandle>)>) {
+        let mut rect_moved = rect;
+        rect_moved.pos += delta;
+        if delta.is_zero() {
+            return (rect_moved, None);
+        }
+
+        let (layers, collision_mask, shape) = match self.bodies.get(handle) {
+            Some(body) if body.kind != BodyKind::Sensor => {
+                (body.layers, self.collision_mask(body.layers), body.shape)
+            }
+            _ => return (rect_moved, None),
+        };
+        let dynamic_region = GridRegion::bounding(
+            self.create_region(rect.pos, rect.size),
+            self.create_region(rect_moved.pos, rect.size),
+        );
+
+        // Round hits have no side to snap flush against, see `sweep_round`
+        let mut closest: Option<(f32, Option<RectSide>, Rect, Option<PoolHandle>)> = None;
+        let mut check_hit = |time: f32, side: Option<RectSide>, rect_other, blocker| {
+            let is_closest = match closest {
+                Some((closest_time, _, _, _)) => time < closest_time,
+                None => true,
+            };
+            if is_closest {
+                closest = Some((time, side, rect_other, blocker));
+            }
+        };
+
+        for (nearby_handle, nearby_body) in self.get_region_bodies(dynamic_region, handle) {
+            if !nearby_body.layers.intersects(collision_mask)
+                || nearby_body.kind == BodyKind::Sensor
+            {
+                continue;
+            }
+
+            let rect_other = Rect::from(nearby_body);
+            if shape != Shape::Rect || nearby_body.shape != Shape::Rect {
+                let time = sweep_round(
+                    shape,
+                    &rect,
+                    delta,
+                    nearby_body.shape,
+                    &rect_other,
+                    nearby_body.blocks,
+                );
+                if let Some(time) = time {
+                    check_hit(time, None, rect_other, Some(nearby_handle));
+                }
+            } else if let Some(hit) = rect
+                .sweep(delta, &rect_other)
+                .filter(|hit| nearby_body.blocks.contains(hit.side))
+            {
+                check_hit(hit.time, Some(hit.side), rect_other, Some(nearby_handle));
+            }
+        }
+
+        let rect_swept = Rect::new(
+            P2::new(
+                rect.pos.x.min(rect_moved.pos.x),
+                rect.pos.y.min(rect_moved.pos.y),
+            ),
+            rect.size + V2::new(delta.x.abs(), delta.y.abs()),
+        );
+        for (rect_tile, blocks) in self.get_blocking_tiles(&rect_swept, layers, collision_mask) {
+            if shape != Shape::Rect {
+                if let Some(time) =
+                    sweep_round(shape, &rect, delta, Shape::Rect, &rect_tile, blocks)
+                {
+                    check_hit(time, None, rect_tile, None);
+                }
+            } else if let Some(hit) = rect
+                .sweep(delta, &rect_tile)
+                .filter(|hit| blocks.contains(hit.side))
+            {
+                check_hit(hit.time, Some(hit.side), rect_tile, None);
+            }
+        }
+
+        match closest {
+            Some((time, side, rect_other, blocker)) => {
+                // Snap flush against the side we hit, instead of trusting `time`, so floating
+                // point error can't leave us embedded a tiny bit into the other body.
+                match side {
+                    Some(RectSide::Left) => rect_moved.pos.x = rect_other.left() - rect.size.x,
+                    Some(RectSide::Right) => rect_moved.pos.x = rect_other.right(),
+                    Some(RectSide::Top) => rect_moved.pos.y = rect_other.top() - rect.size.y,
+                    Some(RectSide::Bottom) => rect_moved.pos.y = rect_other.bottom(),
+                    // Left slightly overlapping, for `separate` to push back out
+                    None => rect_moved.pos = rect.pos + delta * time,
+                }
+                (rect_moved, Some((rect_other, blocker)))
+            }
+            None => (rect_moved, None),
+        }
+    }
+
+    /// Push the body at `handle`, which moved from `rect_t0` to `rect`, out of any bodies it is
+    /// overlapping, returning where it would end up. Like `sweep_axis`, this doesn't actually move
+    /// anything.
+    ///
+    /// Sweeping only works for rects, so we use this to handle collisions involving any other
+    /// shape instead: first let the body move into whatever it is going to move into (no further
+    /// than `sweep_round` allows), and then push it back out again. As it's only pushed out the
+    /// shortest way possible, this results in it naturally sliding around round obstacles.
+    fn separate(&self, handle: PoolHandle, rect_t0: Rect, rect: Rect) -> Rect {
+        const MAX_ITERATIONS: u32 = 4;
+
+        let (layers, collision_mask, shape) = match self.bodies.get(handle) {
+            Some(body) if body.kind != BodyKind::Sensor => {
+                (body.layers, self.collision_mask(body.layers), body.shape)
+            }
+            _ => return rect,
+        };
+
+        let mut rect_separated = rect;
+        // Getting pushed out of one body can push us into another, so repeat a few times
+        for _ in 0..MAX_ITERATIONS {
+            let mut was_pushed = false;
+            let region = self.create_region(rect_separated.pos, rect_separated.size);
+            let nearby_bodies = self
+                .get_region_bodies(region, handle)
+                .into_iter()
+                .map(|(_, nearby_body)| nearby_body)
+                .filter(|nearby_body| {
+                    nearby_body.layers.intersects(collision_mask)
+                        && nearby_body.kind != BodyKind::Sensor
+                })
+                .map(|nearby_body| {
+                    (
+                        Rect::from(nearby_body),
+                        nearby_body.shape,
+                        nearby_body.blocks,
+                    )
+                });
+            // Tiles are always rects, so rect bodies will have already been swept against them
+            let nearby_tiles = if shape != Shape::Rect {
+                self.get_blocking_tiles(&rect_separated, layers, collision_mask)
+            } else {
+                Vec::new()
+            };
+            let nearby = nearby_bodies
+                .chain(
+                    nearby_tiles
+                        .into_iter()
+                        .map(|(rect_tile, blocks)| (rect_tile, Shape::Rect, blocks)),
+                )
+                .collect::<Vec<_>>();
+
+            for (rect_other, other_shape, blocks) in nearby {
+                if shape == Shape::Rect && other_shape == Shape::Rect {
+                    continue;
+                }
+
+                // A body already partway through a one-way obstacle is let the rest of the way
+                if blocks != RectSides::ALL && shape.overlaps(&rect_t0, other_shape, &rect_other) {
+                    continue;
+                }
+
+                let push = shape
+                    .penetration(&rect_separated, other_shape, &rect_other)
+                    .filter(|push| blocks.contains(push_side(*push)));
+                if let Some(push) = push {
+                    // Rect bodies still sweep, so they don't get shoved into other rects
+                    let (rect_x, _) = self.sweep_axis(handle, rect_separated, V2::new(push.x, 0.));
+                    rect_separated = self.sweep_axis(handle, rect_x, V2::new(0., push.y)).0;
+                    was_pushed = true;
+                }
+            }
+
+            if !was_pushed {
+                break;
+            }
+        }
+        rect_separated
+    }
+
+    /// Return the rects of everything which would stop the body at `handle` from fitting into
+    /// `rect`. See `is_free`.
+    fn get_blockers_at(&self, handle: PoolHandle, rect: Rect) -> Vec<Rect> {
+        let (layers, collision_mask, shape) = match self.bodies.get(handle) {
+            Some(body) if body.kind != BodyKind::Sensor => {
+                (body.layers, self.collision_mask(body.layers), body.shape)
+            }
+            _ => return Vec::with_capacity(0),
+        };
+
+        let blocking_bodies = self
+            .get_region_bodies(self.create_region(rect.pos, rect.size), handle)
+            .into_iter()
+            .filter(|(_, other_body)| {
+                other_body.layers.intersects(collision_mask)
+                    && other_body.kind != BodyKind::Sensor
+                    && other_body.blocks == RectSides::ALL
+                    && shape.overlaps(&rect, other_body.shape, &Rect::from(*other_body))
+            })
+            .map(|(_, other_body)| Rect::from(other_body));
+        let blocking_tiles = self
+            .get_blocking_tiles(&rect, layers, collision_mask)
+            .into_iter()
+            .filter(|&(rect_tile, blocks)| {
+                blocks == RectSides::ALL && shape.overlaps(&rect, Shape::Rect, &rect_tile)
+            })
+            .map(|(rect_tile, _)| rect_tile);
+        blocking_bodies.chain(blocking_tiles).collect()
+    }
+
+    /// Return the (merged) rects of all tiles near `rect` which block bodies in `layers`, along
+    /// with which of their sides do the blocking. The layers' `collision_mask` is passed in as
+    /// well to save working it out again.
+    fn get_blocking_tiles(
+        &self,
+        rect: &Rect,
+        layers: LayerMask,
+        collision_mask: LayerMask,
+    ) -> Vec<(Rect, RectSides)> {
+        match &self.tiles {
+            Some(tiles) if collision_mask.intersects(tiles.layer()) => {
+                let blocking = tiles.blocking(layers);
+                tiles
+                    .merged_rects_near(rect, blocking)
+                    .into_iter()
+                    .map(|(rect_tile, flags)| (rect_tile, flags.blocked_sides(blocking)))
+                    .collect()
+            }
+            _ => Vec::with_capacity(0),
+        }
+    }
+
+    /// Like `sweep_axis`, except if the body runs into another body which yields to it, that body
+    /// will be pushed out of the way first. `depth` tracks how long a chain of bodies is being
+    /// pushed, so we can give up at some point.
+    fn push_axis(&mut self, handle: PoolHandle, rect: Rect, delta: V2, depth: u32) -> Rect {
+        const MAX_PUSH_DEPTH: u32 = 8;
+
+        let (rect_moved, blocker) = self.sweep_axis(handle, rect, delta);
+        if let Some(blocker) = blocker {
+            if depth < MAX_PUSH_DEPTH && self.yields_to(blocker, handle) {
+                let rect_blocker = Rect::from(self.bodies.get(blocker).unwrap());
+                let push_delta = (rect.pos + delta) - rect_moved.pos;
+                let rect_pushed = self.push_axis(blocker, rect_blocker, push_delta, depth + 1);
+                self.set_body_pos(blocker, rect_pushed.pos);
+
+                // The blocker got out of our way (as much as it could), so try again
+                return self.sweep_axis(handle, rect, delta).0;
+            }
+        }
+        rect_moved
+    }
+
+    /// Returns every layer that a body in any of `layers` can collide with.
+    fn collision_mask(&self, layers: LayerMask) -> LayerMask {
+        layers.iter().fold(LayerMask::NONE, |mask, layer| {
+            mask | self.collision_masks[layer.index() as usize]
+        })
+    }
+
+    /// Returns `true` if the body at `handle` gives way when the body at `other` runs into it.
+    fn yields_to(&self, handle: PoolHandle, other: PoolHandle) -> bool {
+        match (self.bodies.get(handle), self.bodies.get(other)) {
+            (Some(body), _) if body.kind == BodyKind::Kinematic => false,
+            (Some(body), Some(other_body)) => body.layers.iter().any(|layer| {
+                self.yield_masks[layer.index() as usize].intersects(other_body.layers)
+            }),
+            _ => false,
+        }
+    }
+
+    /// Move a body to a new position, making sure the grid stays in sync with it.
+    fn set_body_pos(&mut self, handle: PoolHandle, pos: P2) {
+        if let Some(size) = self.bodies.get(handle).map(|body| body.size) {
+            self.grid.insert(handle, self.create_region(pos, size));
+            self.bodies.get_mut(handle).unwrap().pos = pos;
+            self.moved_handles.insert(handle);
+        }
+    }
+
+    /// Start or stop treating the body at `handle` as moving, depending on whether it is.
+    fn update_moving(&mut self, handle: PoolHandle) {
+        match self.bodies.get(handle) {
+            Some(body) if !body.vel.is_zero() || !body.accel.is_zero() => {
+                self.moving_handles.insert(handle);
+            }
+            _ => {
+                self.moving_handles.remove(&handle);
+            }
+        }
     }
 
     fn create_region(&self, pos: P2, size: V2) -> GridRegion {
         let tl = pos / self.partition_size;
         let br = (pos + size) / self.partition_size;
-        let coord_tl = GridCoord::new(tl.x as i16, tl.y as i16);
-        let coord_br = GridCoord::new(br.x as i16, br.y as i16);
+        // Floor rather than truncate, so negative coordinates map to cells the same size as
+        // positive ones (which matters when walking the grid cell by cell, e.g. in `raycast`)
+        let coord_tl = GridCoord::new(tl.x.floor() as i16, tl.y.floor() as i16);
+        let coord_br = GridCoord::new(br.x.floor() as i16, br.y.floor() as i16);
 
         GridRegion::with_coords(coord_tl, coord_br)
     }
 }
+
+/// Returns the side of a body that another body, which needs to move by `push` to get out of it,
+/// would be pushed out through.
+fn push_side(push: V2) -> RectSide {
+    if push.x.abs() > push.y.abs() {
+        if push.x < 0. {
+            RectSide::Left
+        } else {
+            RectSide::Right
+        }
+    } else if push.y < 0. {
+        RectSide::Top
+    } else {
+        RectSide::Bottom
+    }
+}
+
+/// Sweeping only works for rects, so when moving `shape`, fit inside `rect`, by `delta` towards
+/// `other_shape`, fit inside `rect_other`, step along from where their rects first meet instead.
+/// Steps are small enough that neither shape can skip past the other, and that the first one
+/// where they overlap leaves them only a little overlapped, so `separate` pushes them back apart
+/// the way they came. Returns how far along `delta` that first step is.
+///
+/// Shapes which already overlap are left to `separate`, as are ones which only start
+/// overlapping on the very last step, which keeps slow bodies sliding smoothly.
+fn sweep_round(
+    shape: Shape,
+    rect: &Rect,
+    delta: V2,
+    other_shape: Shape,
+    rect_other: &Rect,
+    blocks: RectSides,
+) -> Option<f32> {
+    const MIN_STEP: f32 = 0.25;
+
+    let hit = rect
+        .sweep(delta, rect_other)
+        .filter(|hit| blocks.contains(hit.side))?;
+    if shape.overlaps(rect, other_shape, rect_other) {
+        return None;
+    }
+
+    let min_size = rect
+        .size
+        .x
+        .min(rect.size.y)
+        .min(rect_other.size.x)
+        .min(rect_other.size.y);
+    let step = (min_size / 4.).max(MIN_STEP) / delta.len();
+    let mut time = hit.time;
+    loop {
+        time = (time + step).min(1.);
+        let rect_moved = Rect::new(rect.pos + delta * time, rect.size);
+        if shape.overlaps(&rect_moved, other_shape, rect_other) {
+            return if time < 1. { Some(time) } else { None };
+        }
+        if time >= 1. || !rect_moved.overlaps(rect_other) {
+            return None;
+        }
+    }
+}
+
+/// Floats can't be hashed directly, but their exact bits can.
+fn hash_f32s<H: Hasher>(values: &[f32], hasher: &mut H) {
+    for value in values {
+        value.to_bits().hash(hasher);
+    }
+}
+
+fn duration_secs(duration: Duration) -> f32 {
+    duration.as_secs() as f32 + duration.subsec_micros() as f32 / 1_000_000f32
+}
@@ -0,0 +1,232 @@
+//! Collision layers, which decide which bodies in a `CollisionWorld` interact with each other.
+//! Every body belongs to one or more layers, and `CollisionWorldParams::layer_pairs` says which
+//! layers collide.
+
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::ops::{BitAnd, BitOr, BitOrAssign, Not, Sub};
+
+/// A single collision layer. There are 32 of them to go around, each of which must be registered
+/// with a `LayerRegistry` before a `CollisionWorld` will accept it.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(try_from = "u32"))]
+pub struct Layer(u32);
+
+impl Layer {
+    /// The most layers that can exist at once.
+    pub const COUNT: u32 = 32;
+
+    /// Returns the layer at `index`, which must be less than `Layer::COUNT`.
+    pub const fn new(index: u32) -> Layer {
+        if index >= Layer::COUNT {
+            panic!("Invalid layer index")
+        }
+        Layer(1 << index)
+    }
+
+    pub fn index(self) -> u32 {
+        self.0.trailing_zeros()
+    }
+
+    /// Returns the single bit this layer occupies in a `LayerMask`.
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+}
+
+impl fmt::Debug for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Layer({})", self.index())
+    }
+}
+
+/// Layers can be created from raw bits, but only if exactly one bit is set; anything else could
+/// never collide with anything.
+impl TryFrom<u32> for Layer {
+    type Error = InvalidLayer;
+
+    fn try_from(bits: u32) -> Result<Layer, InvalidLayer> {
+        if bits.count_ones() == 1 {
+            Ok(Layer(bits))
+        } else {
+            Err(InvalidLayer(bits))
+        }
+    }
+}
+
+/// The error returned when converting bits which aren't a single layer into a `Layer`.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct InvalidLayer(pub u32);
+
+impl fmt::Display for InvalidLayer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Invalid layer bits: {:#b}", self.0)
+    }
+}
+
+impl Error for InvalidLayer {}
+
+/// A set of layers. Combine layers (or masks) with `|`, intersect them with `&`, and remove them
+/// with `-`.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LayerMask(u32);
+
+impl LayerMask {
+    pub const NONE: LayerMask = LayerMask(0);
+    pub const ALL: LayerMask = LayerMask(!0);
+
+    pub fn from_bits(bits: u32) -> LayerMask {
+        LayerMask(bits)
+    }
+
+    pub fn bits(self) -> u32 {
+        self.0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns `true` if every layer in `other` is also in `self`.
+    pub fn contains<M: Into<LayerMask>>(self, other: M) -> bool {
+        let other = other.into();
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns `true` if any layer in `other` is also in `self`.
+    pub fn intersects<M: Into<LayerMask>>(self, other: M) -> bool {
+        self.0 & other.into().0 != 0
+    }
+
+    /// Iterate over every layer in this mask, from lowest index to highest.
+    pub fn iter(self) -> impl Iterator<Item = Layer> {
+        (0..Layer::COUNT)
+            .map(Layer::new)
+            .filter(move |layer| self.0 & layer.0 != 0)
+    }
+}
+
+impl From<Layer> for LayerMask {
+    fn from(layer: Layer) -> LayerMask {
+        LayerMask(layer.0)
+    }
+}
+
+impl<M: Into<LayerMask>> BitOr<M> for LayerMask {
+    type Output = LayerMask;
+
+    fn bitor(self, rhs: M) -> LayerMask {
+        LayerMask(self.0 | rhs.into().0)
+    }
+}
+
+impl<M: Into<LayerMask>> BitOr<M> for Layer {
+    type Output = LayerMask;
+
+    fn bitor(self, rhs: M) -> LayerMask {
+        LayerMask(self.0 | rhs.into().0)
+    }
+}
+
+impl<M: Into<LayerMask>> BitOrAssign<M> for LayerMask {
+    fn bitor_assign(&mut self, rhs: M) {
+        self.0 |= rhs.into().0;
+    }
+}
+
+impl<M: Into<LayerMask>> BitAnd<M> for LayerMask {
+    type Output = LayerMask;
+
+    fn bitand(self, rhs: M) -> LayerMask {
+        LayerMask(self.0 & rhs.into().0)
+    }
+}
+
+impl<M: Into<LayerMask>> Sub<M> for LayerMask {
+    type Output = LayerMask;
+
+    fn sub(self, rhs: M) -> LayerMask {
+        LayerMask(self.0 & !rhs.into().0)
+    }
+}
+
+impl Not for LayerMask {
+    type Output = LayerMask;
+
+    fn not(self) -> LayerMask {
+        LayerMask(!self.0)
+    }
+}
+
+/// The names of all layers in use. Pass one into `CollisionWorldParams::layers` to tell a
+/// `CollisionWorld` which layers its bodies may belong to.
+#[derive(Debug, Clone)]
+pub struct LayerRegistry {
+    names: Vec<Option<String>>,
+}
+
+impl LayerRegistry {
+    pub fn new() -> LayerRegistry {
+        LayerRegistry {
+            names: vec![None; Layer::COUNT as usize],
+        }
+    }
+
+    /// Give `layer` a name, marking it as in use. Each layer, and each name, can only be
+    /// registered once.
+    pub fn register(&mut self, layer: Layer, name: &str) {
+        if self.names[layer.index() as usize].is_some() {
+            panic!("Layer already registered: {:?}", layer)
+        }
+        if self.get(name).is_some() {
+            panic!("Layer name already registered: {}", name)
+        }
+        self.names[layer.index() as usize] = Some(name.to_owned());
+    }
+
+    /// Like `register`, but using the lowest layer that hasn't been registered yet.
+    pub fn register_next(&mut self, name: &str) -> Layer {
+        let layer = match self.names.iter().position(|name| name.is_none()) {
+            Some(index) => Layer::new(index as u32),
+            None => panic!("No layers left to register {}", name),
+        };
+        self.register(layer, name);
+        layer
+    }
+
+    /// Look up a registered layer by name.
+    pub fn get(&self, name: &str) -> Option<Layer> {
+        self.names
+            .iter()
+            .position(|n| n.as_deref() == Some(name))
+            .map(|index| Layer::new(index as u32))
+    }
+
+    /// Returns the name `layer` was registered with, if it has been.
+    pub fn name(&self, layer: Layer) -> Option<&str> {
+        self.names[layer.index() as usize].as_deref()
+    }
+
+    /// Returns a mask of every registered layer.
+    pub fn mask(&self) -> LayerMask {
+        self.iter()
+            .fold(LayerMask::NONE, |mask, (layer, _)| mask | layer)
+    }
+
+    /// Iterate over every registered layer, along with its name.
+    pub fn iter(&self) -> impl Iterator<Item = (Layer, &str)> {
+        self.names.iter().enumerate().filter_map(|(index, name)| {
+            name.as_ref()
+                .map(|name| (Layer::new(index as u32), name.as_str()))
+        })
+    }
+}
+
+impl Default for LayerRegistry {
+    fn default() -> LayerRegistry {
+        LayerRegistry::new()
+    }
+}
@@ -0,0 +1,93 @@
+//! Recording what gets fed into a `CollisionWorld`, so it can be played back later. As a world
+//! always ends up in the same state when given the same inputs, playing back a recording is a
+//! cheap way to check that this still holds (e.g. in a regression test), or to reproduce a bug
+//! exactly as it happened.
+
+use super::{BodyHandle, CollisionWorld};
+use crate::geom::V2;
+use std::hash::Hasher;
+use std::time::Duration;
+
+/// The velocities set on bodies before a single call to `CollisionWorld::elapse_time`, and how
+/// much time was passed into it.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReplayFrame {
+    pub velocities: Vec<(BodyHandle, V2)>,
+    pub duration: Duration,
+}
+
+/// A recording of every frame fed into a `CollisionWorld`. See `ReplayFrame`.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Replay {
+    frames: Vec<ReplayFrame>,
+}
+
+impl Replay {
+    pub fn new() -> Replay {
+        Replay { frames: Vec::new() }
+    }
+
+    /// Record that bodies were given new `velocities`, after which `duration` time elapsed.
+    pub fn record(&mut self, velocities: Vec<(BodyHandle, V2)>, duration: Duration) {
+        self.frames.push(ReplayFrame {
+            velocities,
+            duration,
+        });
+    }
+
+    pub fn frames(&self) -> &[ReplayFrame] {
+        &self.frames
+    }
+
+    /// Feed every recorded frame into `world`, in order, returning its `state_hash` after each
+    /// one. Comparing these against another playback shows exactly which frame the two first went
+    /// out of sync on, if they ever do.
+    ///
+    /// For the results to mean anything, `world` must start out in the same state the recording
+    /// did, e.g. by setting it up the same way or restoring the same `WorldSnapshot` into it.
+    /// Handles are passed through `CollisionWorld::rebind`, so the recording can be played into
+    /// any such world, not just the one it was recorded from.
+    ///
+    /// Panics if a frame sets the velocity of a body which isn't in `world`, as the two can only
+    /// go out of sync from there.
+    pub fn play(&self, world: &mut CollisionWorld) -> Vec<u64> {
+        self.frames
+            .iter()
+            .map(|frame| {
+                for &(handle, vel) in frame.velocities.iter() {
+                    match world.rebind(handle) {
+                        Some(handle) => world.set_vel(handle, vel),
+                        None => panic!("Replay doesn't match world: no body for {:?}", handle),
+                    }
+                }
+                world.elapse_time(frame.duration);
+                world.state_hash()
+            })
+            .collect()
+    }
+}
+
+/// A hasher which, unlike the standard library's default, gives the same result every time for
+/// the same input (using FNV-1a).
+pub(super) struct StateHasher(u64);
+
+impl StateHasher {
+    pub fn new() -> StateHasher {
+        StateHasher(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for StateHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! Snapshots of a `CollisionWorld`, which can be restored later to put the world back exactly how
+//! it was, e.g. for save states, rewinding while debugging, or regression tests.
+
+use super::tiles::TileLayer;
+use super::{Body, BodyHandle, ContactEvent, KinematicState, PoolHandle, PushState};
+use crate::{grid::Grid, pool::Pool, shape::RectSide};
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+
+/// Everything about a `CollisionWorld` which changes as it runs. Take one with
+/// `CollisionWorld::snapshot`, and put it back with `CollisionWorld::restore`.
+///
+/// With the `serde` feature enabled, snapshots can be serialized too, e.g. to save them to disk.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WorldSnapshot {
+    /// The world's settings, only kept to make sure the snapshot is restored into a compatible
+    /// world; grid regions are meaningless with a different partition size, for example.
+    pub(super) partition_size: [f32; 2],
+    pub(super) time_step: Duration,
+    pub(super) time_counter: Duration,
+    pub(super) bodies: Pool<Body>,
+    pub(super) grid: Grid<PoolHandle>,
+    pub(super) dirty_handles: BTreeSet<PoolHandle>,
+    pub(super) moved_handles: BTreeSet<PoolHandle>,
+    pub(super) moving_handles: BTreeSet<PoolHandle>,
+    #[cfg_attr(feature = "serde", serde(with = "map_as_seq"))]
+    pub(super) contacts: BTreeMap<(PoolHandle, PoolHandle), RectSide>,
+    pub(super) events: Vec<ContactEvent>,
+    pub(super) tiles: Option<TileLayer>,
+    #[cfg_attr(feature = "serde", serde(with = "map_as_seq"))]
+    pub(super) pushables: BTreeMap<PoolHandle, PushState>,
+    #[cfg_attr(feature = "serde", serde(with = "map_as_seq"))]
+    pub(super) kinematics: BTreeMap<PoolHandle, KinematicState>,
+    pub(super) settled: Vec<BodyHandle>,
+    pub(super) crushed: Vec<BodyHandle>,
+}
+
+/// Many formats (e.g. JSON) only allow strings as map keys, so serialize maps keyed by anything
+/// else as a list of key-value pairs instead.
+#[cfg(feature = "serde")]
+mod map_as_seq {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::collections::BTreeMap;
+
+    pub fn serialize<K, V, S>(map: &BTreeMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        K: Serialize + Ord,
+        V: Serialize,
+        S: Serializer,
+    {
+        serializer.collect_seq(map.iter())
+    }
+
+    pub fn deserialize<'de, K, V, D>(deserializer: D) -> Result<BTreeMap<K, V>, D::Error>
+    where
+        K: Deserialize<'de> + Ord,
+        V: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        let pairs: Vec<(K, V)> = Deserialize::deserialize(deserializer)?;
+        Ok(pairs.into_iter().collect())
+    }
+}
@@ -0,0 +1,469 @@
+//! A static layer of tiles which bodies in a `CollisionWorld` can collide with. Most levels are
+//! largely made up of unmoving, grid-aligned walls, and representing each one with its own `Body`
+//! wastes a lot of space (and time, as every one of them needs to be checked individually).
+
+use super::layers::{Layer, LayerMask};
+use crate::{
+    geom::{P2, V2},
+    shape::{Ray, RayIntersection, Rect, RectSides},
+};
+use std::collections::BTreeMap;
+#[cfg(feature = "serde")]
+use std::convert::TryFrom;
+#[cfg(feature = "serde")]
+use std::error::Error;
+#[cfg(feature = "serde")]
+use std::fmt;
+use std::ops::{BitOr, BitOrAssign};
+
+/// A set of properties describing a single tile, e.g. whether it is a wall or a pool of water.
+/// Combine flags with `|`.
+#[derive(Debug, Copy, Clone, Default, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TileFlags(u8);
+
+impl TileFlags {
+    pub const EMPTY: TileFlags = TileFlags(0);
+    pub const SOLID: TileFlags = TileFlags(1);
+    pub const WATER: TileFlags = TileFlags(1 << 1);
+    pub const HOLE: TileFlags = TileFlags(1 << 2);
+    pub const LEDGE: TileFlags = TileFlags(1 << 3);
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    /// Returns `true` if every flag in `other` is also set in `self`.
+    pub fn contains(self, other: TileFlags) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns `true` if any flag in `other` is also set in `self`.
+    pub fn intersects(self, other: TileFlags) -> bool {
+        self.0 & other.0 != 0
+    }
+
+    /// Returns the sides of a tile with these flags that block a body which is blocked by
+    /// `blocking` tiles (see `TileLayer::set_blocking`).
+    ///
+    /// Ledges are one-way: a body can hop down off of one, passing through its top side, but can't
+    /// climb back up it or walk into it from either side.
+    pub fn blocked_sides(self, blocking: TileFlags) -> RectSides {
+        let flags = TileFlags(self.0 & blocking.0);
+        if flags.is_empty() {
+            RectSides::NONE
+        } else if flags == TileFlags::LEDGE {
+            RectSides::TOP.inverse()
+        } else {
+            RectSides::ALL
+        }
+    }
+}
+
+impl BitOr for TileFlags {
+    type Output = TileFlags;
+
+    fn bitor(self, rhs: TileFlags) -> TileFlags {
+        TileFlags(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for TileFlags {
+    fn bitor_assign(&mut self, rhs: TileFlags) {
+        self.0 |= rhs.0;
+    }
+}
+
+/// A grid of tiles, each with its own `TileFlags`, starting at the origin and extending right and
+/// down. Give one to `CollisionWorld::set_tiles` and moving bodies will collide with it.
+///
+/// Neighboring tiles with the same flags are merged together into larger rects, so that bodies
+/// sliding along a wall never catch on the seams between its tiles.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+    feature = "serde",
+    serde(try_from = "TileLayerData", into = "TileLayerData")
+)]
+pub struct TileLayer {
+    /// The collision layer (see `CollisionWorldParams::layer_pairs`) that tiles belong to
+    layer: Layer,
+    tile_size: V2,
+    num_tiles: (u16, u16),
+    flags: Vec<TileFlags>,
+    /// A mapping of layers to the flags that block them, for any that don't use the default
+    blocking: BTreeMap<Layer, TileFlags>,
+    merged: Vec<(Rect, TileFlags)>,
+    /// For each tile, an index into `merged`, or `None` if the tile is empty
+    merged_indices: Vec<Option<usize>>,
+}
+
+/// The parts of a `TileLayer` that get saved. The merged rects are rebuilt from the flags when
+/// it's loaded back in, rather than trusting that they still match.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct TileLayerData {
+    layer: Layer,
+    tile_size: V2,
+    num_tiles: (u16, u16),
+    flags: Vec<TileFlags>,
+    blocking: BTreeMap<Layer, TileFlags>,
+}
+
+#[cfg(feature = "serde")]
+impl From<TileLayer> for TileLayerData {
+    fn from(tile_layer: TileLayer) -> TileLayerData {
+        TileLayerData {
+            layer: tile_layer.layer,
+            tile_size: tile_layer.tile_size,
+            num_tiles: tile_layer.num_tiles,
+            flags: tile_layer.flags,
+            blocking: tile_layer.blocking,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl TryFrom<TileLayerData> for TileLayer {
+    type Error = InvalidTileLayer;
+
+    fn try_from(data: TileLayerData) -> Result<TileLayer, InvalidTileLayer> {
+        let num_flags = data.num_tiles.0 as usize * data.num_tiles.1 as usize;
+        if data.tile_size.x <= 0. || data.tile_size.y <= 0. || data.flags.len() != num_flags {
+            return Err(InvalidTileLayer {
+                tile_size: data.tile_size,
+                num_tiles: data.num_tiles,
+                num_flags: data.flags.len(),
+            });
+        }
+
+        let mut tile_layer = TileLayer {
+            layer: data.layer,
+            tile_size: data.tile_size,
+            num_tiles: data.num_tiles,
+            flags: data.flags,
+            blocking: data.blocking,
+            merged: Vec::new(),
+            merged_indices: Vec::new(),
+        };
+        tile_layer.merge();
+        Ok(tile_layer)
+    }
+}
+
+/// The error returned when loading a `TileLayer` with a tile size that isn't positive, or a
+/// different number of tile flags than it has tiles.
+#[cfg(feature = "serde")]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct InvalidTileLayer {
+    pub tile_size: V2,
+    pub num_tiles: (u16, u16),
+    pub num_flags: usize,
+}
+
+#[cfg(feature = "serde")]
+impl fmt::Display for InvalidTileLayer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Invalid tile layer: {} flags for {:?} tiles of size {:?}",
+            self.num_flags, self.num_tiles, self.tile_size
+        )
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Error for InvalidTileLayer {}
+
+impl TileLayer {
+    /// Create a new layer `num_tiles` wide and tall, using `init` to set the flags for the tile at
+    /// each `(x, y)` index.
+    pub fn new<F>(layer: Layer, tile_size: V2, num_tiles: (u16, u16), init: F) -> TileLayer
+    where
+        F: Fn(u16, u16) -> TileFlags,
+    {
+        if tile_size.x <= 0. || tile_size.y <= 0. {
+            panic!("Invalid tile size: {:?}", tile_size)
+        }
+
+        let mut flags = Vec::with_capacity(num_tiles.0 as usize * num_tiles.1 as usize);
+        for y in 0..num_tiles.1 {
+            for x in 0..num_tiles.0 {
+                flags.push(init(x, y));
+            }
+        }
+
+        let mut tile_layer = TileLayer {
+            layer,
+            tile_size,
+            num_tiles,
+            flags,
+            blocking: BTreeMap::new(),
+            merged: Vec::new(),
+            merged_indices: Vec::new(),
+        };
+        tile_layer.merge();
+        tile_layer
+    }
+
+    pub fn layer(&self) -> Layer {
+        self.layer
+    }
+
+    pub fn tile_size(&self) -> V2 {
+        self.tile_size
+    }
+
+    pub fn num_tiles(&self) -> (u16, u16) {
+        self.num_tiles
+    }
+
+    /// Set which tiles block bodies in `layer`, overriding the default of `TileFlags::SOLID` and
+    /// `TileFlags::LEDGE` tiles. For example, walking bodies may want to stay out of water and
+    /// holes too, while flying bodies only care about walls.
+    ///
+    /// This only matters for layers that can collide with this tile layer's own layer in the
+    /// first place.
+    pub fn set_blocking(&mut self, layer: Layer, flags: TileFlags) {
+        self.blocking.insert(layer, flags);
+    }
+
+    /// Returns the flags for tiles which block bodies in any of `layers`. See `set_blocking`.
+    pub fn blocking<L: Into<LayerMask>>(&self, layers: L) -> TileFlags {
+        layers.into().iter().fold(TileFlags::EMPTY, |flags, layer| {
+            flags
+                | *self
+                    .blocking
+                    .get(&layer)
+                    .unwrap_or(&(TileFlags::SOLID | TileFlags::LEDGE))
+        })
+    }
+
+    /// Returns the flags for the tile at index `(x, y)`, which are empty if out of bounds.
+    pub fn get(&self, x: u16, y: u16) -> TileFlags {
+        match self.index(x, y) {
+            Some(i) => self.flags[i],
+            None => TileFlags::EMPTY,
+        }
+    }
+
+    /// Change the flags for the tile at index `(x, y)`.
+    ///
+    /// As this re-merges every tile in the layer, it's intended for occasional changes (e.g. a
+    /// door opening) rather than building up a layer tile by tile; use `new` for that instead.
+    pub fn set(&mut self, x: u16, y: u16, flags: TileFlags) {
+        match self.index(x, y) {
+            Some(i) => self.flags[i] = flags,
+            None => panic!("Tile out of bounds: ({}, {})", x, y),
+        }
+        self.merge();
+    }
+
+    /// Returns the flags for the tile containing the point `pt`.
+    pub fn flags_at(&self, pt: P2) -> TileFlags {
+        self.flags_in(&Rect::new(pt, V2::zero()))
+    }
+
+    /// Returns the combined flags for all tiles overlapping `rect`, e.g. to check if a body is
+    /// standing in any water.
+    pub fn flags_in(&self, rect: &Rect) -> TileFlags {
+        let mut flags = TileFlags::EMPTY;
+        if let Some((tl, br)) = self.tile_range(rect, false) {
+            for y in tl.1..=br.1 {
+                for x in tl.0..=br.0 {
+                    flags |= self.get(x, y);
+                }
+            }
+        }
+        flags
+    }
+
+    /// Iterate over all non-empty tiles, merged together into rects where possible.
+    pub fn merged_rects(&self) -> impl Iterator<Item = (Rect, TileFlags)> + '_ {
+        self.merged.iter().cloned()
+    }
+
+    /// Returns every merged rect overlapping or touching `rect` with any of `flags` set.
+    pub(crate) fn merged_rects_near(
+        &self,
+        rect: &Rect,
+        flags: TileFlags,
+    ) -> Vec<(Rect, TileFlags)> {
+        let mut indices = Vec::new();
+        if let Some((tl, br)) = self.tile_range(rect, true) {
+            for y in tl.1..=br.1 {
+                for x in tl.0..=br.0 {
+                    if let Some(i) = self.index(x, y).and_then(|i| self.merged_indices[i]) {
+                        if self.merged[i].1.intersects(flags) && !indices.contains(&i) {
+                            indices.push(i);
+                        }
+                    }
+                }
+            }
+        }
+        indices.into_iter().map(|i| self.merged[i]).collect()
+    }
+
+    /// Cast `ray` through the layer, up to a distance of `max_dist`, returning where it first hits
+    /// a tile with any of `flags` set.
+    pub fn raycast(&self, ray: Ray, max_dist: f32, flags: TileFlags) -> Option<RayIntersection> {
+        // Same DDA approach as `CollisionWorld::raycast`, but walking tiles instead of grid cells
+        let tile_origin = ray.origin / [self.tile_size.x, self.tile_size.y];
+        let mut tile = [tile_origin.x.floor() as i32, tile_origin.y.floor() as i32];
+        let mut tile_step = [0, 0];
+        let mut dist_next = [f32::INFINITY, f32::INFINITY];
+        let mut dist_delta = [f32::INFINITY, f32::INFINITY];
+        for axis in 0..2 {
+            let (origin, dir, size) = if axis == 0 {
+                (ray.origin.x, ray.dir.x, self.tile_size.x)
+            } else {
+                (ray.origin.y, ray.dir.y, self.tile_size.y)
+            };
+
+            if dir > 0. {
+                tile_step[axis] = 1;
+                dist_next[axis] = ((tile[axis] + 1) as f32 * size - origin) / dir;
+                dist_delta[axis] = size / dir;
+            } else if dir < 0. {
+                tile_step[axis] = -1;
+                dist_next[axis] = (tile[axis] as f32 * size - origin) / dir;
+                dist_delta[axis] = size / -dir;
+            }
+        }
+
+        loop {
+            let is_in_bounds = tile[0] >= 0
+                && tile[1] >= 0
+                && tile[0] < i32::from(self.num_tiles.0)
+                && tile[1] < i32::from(self.num_tiles.1);
+            if is_in_bounds && self.get(tile[0] as u16, tile[1] as u16).intersects(flags) {
+                let rect_tile = Rect::new(
+                    P2::new(
+                        tile[0] as f32 * self.tile_size.x,
+                        tile[1] as f32 * self.tile_size.y,
+                    ),
+                    self.tile_size,
+                );
+                if let Some(intersection) = ray.intersect_rect(&rect_tile) {
+                    if intersection.distance <= max_dist {
+                        return Some(intersection);
+                    }
+                }
+            }
+
+            // Stop once we've passed max_dist or are heading away from the layer for good
+            let dist_tile_exit = dist_next[0].min(dist_next[1]);
+            let is_leaving = (0..2).any(|axis| {
+                let max = i32::from(if axis == 0 {
+                    self.num_tiles.0
+                } else {
+                    self.num_tiles.1
+                });
+                (tile[axis] < 0 && tile_step[axis] <= 0)
+                    || (tile[axis] >= max && tile_step[axis] >= 0)
+            });
+            if dist_tile_exit > max_dist || dist_tile_exit.is_infinite() || is_leaving {
+                return None;
+            }
+
+            let axis = if dist_next[0] < dist_next[1] { 0 } else { 1 };
+            tile[axis] += tile_step[axis];
+            dist_next[axis] += dist_delta[axis];
+        }
+    }
+
+    fn index(&self, x: u16, y: u16) -> Option<usize> {
+        if x < self.num_tiles.0 && y < self.num_tiles.1 {
+            Some(y as usize * self.num_tiles.0 as usize + x as usize)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the indices of the top-left and bottom-right tiles overlapping `rect`, clamped to
+    /// the layer, or `None` if `rect` is entirely outside it. If `include_touching` is set, tiles
+    /// which are only flush against `rect` are included as well.
+    fn tile_range(&self, rect: &Rect, include_touching: bool) -> Option<((u16, u16), (u16, u16))> {
+        let tl = rect.pos / [self.tile_size.x, self.tile_size.y];
+        let br = (rect.pos + rect.size) / [self.tile_size.x, self.tile_size.y];
+        let (mut tl_x, mut tl_y) = (tl.x.floor(), tl.y.floor());
+        // The right / bottom edges of a rect aren't part of it, unless they're its only edge
+        let (mut br_x, mut br_y) = (
+            if rect.size.x > 0. {
+                br.x.ceil() - 1.
+            } else {
+                br.x.floor()
+            },
+            if rect.size.y > 0. {
+                br.y.ceil() - 1.
+            } else {
+                br.y.floor()
+            },
+        );
+        if include_touching {
+            if tl_x == tl.x {
+                tl_x -= 1.;
+            }
+            if tl_y == tl.y {
+                tl_y -= 1.;
+            }
+            if br_x + 1. == br.x {
+                br_x += 1.;
+            }
+            if br_y + 1. == br.y {
+                br_y += 1.;
+            }
+        }
+
+        let max_x = f32::from(self.num_tiles.0) - 1.;
+        let max_y = f32::from(self.num_tiles.1) - 1.;
+        if br_x < 0. || br_y < 0. || tl_x > max_x || tl_y > max_y {
+            return None;
+        }
+        Some((
+            (tl_x.max(0.) as u16, tl_y.max(0.) as u16),
+            (br_x.min(max_x) as u16, br_y.min(max_y) as u16),
+        ))
+    }
+
+    /// Greedily merge neighboring tiles with the same flags into rects, growing each one as wide
+    /// as possible first and then as tall as possible.
+    fn merge(&mut self) {
+        let (num_x, num_y) = (self.num_tiles.0 as usize, self.num_tiles.1 as usize);
+        self.merged.clear();
+        self.merged_indices = vec![None; self.flags.len()];
+
+        for y in 0..num_y {
+            for x in 0..num_x {
+                let flags = self.flags[y * num_x + x];
+                if flags.is_empty() || self.merged_indices[y * num_x + x].is_some() {
+                    continue;
+                }
+
+                let is_mergeable = |x: usize, y: usize, merged_indices: &[Option<usize>]| {
+                    self.flags[y * num_x + x] == flags && merged_indices[y * num_x + x].is_none()
+                };
+                let mut w = 1;
+                while x + w < num_x && is_mergeable(x + w, y, &self.merged_indices) {
+                    w += 1;
+                }
+                let mut h = 1;
+                while y + h < num_y
+                    && (x..x + w).all(|x| is_mergeable(x, y + h, &self.merged_indices))
+                {
+                    h += 1;
+                }
+
+                let index = self.merged.len();
+                for merged_y in y..y + h {
+                    for merged_x in x..x + w {
+                        self.merged_indices[merged_y * num_x + merged_x] = Some(index);
+                    }
+                }
+                let pos = P2::new(x as f32 * self.tile_size.x, y as f32 * self.tile_size.y);
+                let size = V2::new(w as f32 * self.tile_size.x, h as f32 * self.tile_size.y);
+                self.merged.push((Rect::new(pos, size), flags));
+            }
+        }
+    }
+}
@@ -0,0 +1,507 @@
+//! Storage for entities built out of components, where each kind of component (a position, a
+//! sprite, health, ...) lives in its own column instead of on one big struct per entity.
+//!
+//! `Entities` hands out an `EntityHandle` for each entity, and that one handle indexes into every
+//! column. Columns are keyed by the slot the entity occupies, and since slots never move, neither
+//! do components. A `DenseColumn` suits components which most entities have, and a `SparseColumn`
+//! suits ones which only a few do. Use `join` and friends to visit the entities which have all of
+//! several components.
+
+use crate::pool::{Handle, Pool};
+use std::slice;
+
+/// Marker for what `Entities` holds. Entities have no data of their own, only components.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Entity {
+    _private: (),
+}
+
+/// A handle to an entity, which can be used with every column its components live in.
+pub type EntityHandle = Handle<Entity>;
+
+/// Keeps track of which entities are alive.
+///
+/// Columns only know about the entities they were given components for, so despawning an entity
+/// takes the columns to remove its components from as well. Components left behind in a column
+/// are never matched by handles to other entities, and joins skip them, but they're still visited
+/// when iterating over the column by itself.
+///
+/// Like a `Pool`, cloned and deserialized entities don't accept the original's handles. Columns
+/// cloned or loaded along with them have to be passed through `rebind_columns` before they can be
+/// used together, and any other handles kept around through `rebind`.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Entities {
+    pool: Pool<Entity>,
+}
+
+#[allow(clippy::new_without_default)] // API is intentionally explicit
+impl Entities {
+    pub fn new() -> Entities {
+        Entities { pool: Pool::new() }
+    }
+
+    pub fn with_capacity(capacity: usize) -> Entities {
+        Entities {
+            pool: Pool::with_capacity(capacity),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.pool.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pool.is_empty()
+    }
+
+    /// Create a new entity, without any components.
+    pub fn spawn(&mut self) -> EntityHandle {
+        self.pool.push(Entity { _private: () })
+    }
+
+    /// Remove `entity`, along with its components in each of `columns`. Returns `false` if the
+    /// entity was already despawned, in which case nothing is removed.
+    pub fn despawn(&mut self, entity: EntityHandle, columns: &mut [&mut dyn AnyColumn]) -> bool {
+        if self.pool.remove(entity).is_none() {
+            return false;
+        }
+        for column in columns.iter_mut() {
+            column.remove_entity(entity);
+        }
+        true
+    }
+
+    /// Turn a handle to an entity in a copy of these entities (or one that was saved along with
+    /// them and loaded back in) into one that works here, or `None` if the entity isn't alive.
+    pub fn rebind(&self, entity: EntityHandle) -> Option<EntityHandle> {
+        self.pool.rebind(entity)
+    }
+
+    /// Point every component in each of `columns` at these entities, e.g. after cloning or loading
+    /// them together. Components of entities which aren't alive here are removed.
+    pub fn rebind_columns(&self, columns: &mut [&mut dyn AnyColumn]) {
+        for column in columns.iter_mut() {
+            column.rebind_entities(self);
+        }
+    }
+
+    /// Returns `true` if `entity` hasn't been despawned.
+    pub fn contains(&self, entity: EntityHandle) -> bool {
+        self.pool.contains(entity)
+    }
+
+    /// Return an iterator over the handles of every entity which is alive.
+    pub fn iter(&self) -> impl Iterator<Item = EntityHandle> + '_ {
+        self.pool.iter_with_handles().map(|(entity, _)| entity)
+    }
+}
+
+/// Storage for one kind of component, keyed by entity.
+///
+/// Each component remembers which entity it belongs to, so a handle to a despawned entity never
+/// finds the component of whichever entity took over its slot.
+pub trait Column {
+    type Item;
+    type Iter<'a>: Iterator<Item = (EntityHandle, &'a Self::Item)>
+    where
+        Self: 'a;
+    type IterMut<'a>: Iterator<Item = (EntityHandle, &'a mut Self::Item)>
+    where
+        Self: 'a;
+
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Give `entity` a component, returning the one it had before, if any.
+    fn insert(&mut self, entity: EntityHandle, value: Self::Item) -> Option<Self::Item>;
+
+    /// Take `entity`'s component away, if it has one.
+    fn remove(&mut self, entity: EntityHandle) -> Option<Self::Item>;
+
+    fn get(&self, entity: EntityHandle) -> Option<&Self::Item>;
+
+    /// Mutable version of `get`.
+    fn get_mut(&mut self, entity: EntityHandle) -> Option<&mut Self::Item>;
+
+    /// Rebind the entity of every component through `Entities::rebind`, removing those it returns
+    /// `None` for. See `Entities::rebind_columns`.
+    fn rebind(&mut self, entities: &Entities);
+
+    fn contains(&self, entity: EntityHandle) -> bool {
+        self.get(entity).is_some()
+    }
+
+    /// Return an iterator over every component in this column, along with its entity.
+    fn iter(&self) -> Self::Iter<'_>;
+
+    /// Mutable version of `iter`.
+    fn iter_mut(&mut self) -> Self::IterMut<'_>;
+}
+
+/// The part of `Column` which doesn't depend on what the column holds, so that columns of
+/// different components can be passed to `Entities::despawn` together.
+pub trait AnyColumn {
+    /// Remove `entity`'s component from this column, returning `false` if it didn't have one.
+    fn remove_entity(&mut self, entity: EntityHandle) -> bool;
+
+    /// See `Column::rebind`.
+    fn rebind_entities(&mut self, entities: &Entities);
+}
+
+impl<C: Column> AnyColumn for C {
+    fn remove_entity(&mut self, entity: EntityHandle) -> bool {
+        self.remove(entity).is_some()
+    }
+
+    fn rebind_entities(&mut self, entities: &Entities) {
+        self.rebind(entities)
+    }
+}
+
+/// A column with room for a component in every slot, up to the highest one given a component.
+/// Lookups are as cheap as indexing an array, but iterating visits empty slots too.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DenseColumn<T> {
+    entries: Vec<Option<(EntityHandle, T)>>,
+    len: usize,
+}
+
+#[allow(clippy::new_without_default)] // API is intentionally explicit
+impl<T> DenseColumn<T> {
+    pub fn new() -> DenseColumn<T> {
+        DenseColumn {
+            entries: Vec::new(),
+            len: 0,
+        }
+    }
+}
+
+impl<T> Column for DenseColumn<T> {
+    type Item = T;
+    type Iter<'a>
+        = DenseIter<'a, T>
+    where
+        T: 'a;
+    type IterMut<'a>
+        = DenseIterMut<'a, T>
+    where
+        T: 'a;
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn insert(&mut self, entity: EntityHandle, value: T) -> Option<T> {
+        let index = entity.index();
+        if index >= self.entries.len() {
+            self.entries.resize_with(index + 1, || None);
+        }
+
+        match self.entries[index].replace((entity, value)) {
+            Some((owner, old_value)) if owner == entity => Some(old_value),
+            // Whatever was left behind by a despawned entity is simply overwritten
+            Some(_) => None,
+            None => {
+                self.len += 1;
+                None
+            }
+        }
+    }
+
+    fn remove(&mut self, entity: EntityHandle) -> Option<T> {
+        let entry = self.entries.get_mut(entity.index())?;
+        match entry {
+            Some((owner, _)) if *owner == entity => {
+                self.len -= 1;
+                entry.take().map(|(_, value)| value)
+            }
+            _ => None,
+        }
+    }
+
+    fn get(&self, entity: EntityHandle) -> Option<&T> {
+        match self.entries.get(entity.index())? {
+            Some((owner, value)) if *owner == entity => Some(value),
+            _ => None,
+        }
+    }
+
+    fn get_mut(&mut self, entity: EntityHandle) -> Option<&mut T> {
+        match self.entries.get_mut(entity.index())? {
+            Some((owner, value)) if *owner == entity => Some(value),
+            _ => None,
+        }
+    }
+
+    fn rebind(&mut self, entities: &Entities) {
+        for entry in self.entries.iter_mut() {
+            if let Some((owner, _)) = entry {
+                match entities.rebind(*owner) {
+                    Some(rebound) => *owner = rebound,
+                    None => {
+                        *entry = None;
+                        self.len -= 1;
+                    }
+                }
+            }
+        }
+    }
+
+    fn iter(&self) -> DenseIter<'_, T> {
+        DenseIter {
+            entries: self.entries.iter(),
+        }
+    }
+
+    fn iter_mut(&mut self) -> DenseIterMut<'_, T> {
+        DenseIterMut {
+            entries: self.entries.iter_mut(),
+        }
+    }
+}
+
+/// Iterator over the components in a `DenseColumn`. See `Column::iter`.
+pub struct DenseIter<'a, T> {
+    entries: slice::Iter<'a, Option<(EntityHandle, T)>>,
+}
+
+impl<'a, T> Iterator for DenseIter<'a, T> {
+    type Item = (EntityHandle, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries
+            .find_map(|entry| entry.as_ref().map(|(owner, value)| (*owner, value)))
+    }
+}
+
+/// Iterator over the components in a `DenseColumn`. See `Column::iter_mut`.
+pub struct DenseIterMut<'a, T> {
+    entries: slice::IterMut<'a, Option<(EntityHandle, T)>>,
+}
+
+impl<'a, T> Iterator for DenseIterMut<'a, T> {
+    type Item = (EntityHandle, &'a mut T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries
+            .find_map(|entry| entry.as_mut().map(|(owner, value)| (*owner, value)))
+    }
+}
+
+/// A column which packs its components together, with a small lookup table from slots to where
+/// their component is. Lookups take one extra step compared to a `DenseColumn`, but iterating only
+/// visits the components which exist. Removing a component moves the last one into its place, so
+/// the order components are visited in changes over time.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SparseColumn<T> {
+    /// For each slot, where its component is in `values`
+    slots: Vec<Option<usize>>,
+    values: Vec<(EntityHandle, T)>,
+}
+
+#[allow(clippy::new_without_default)] // API is intentionally explicit
+impl<T> SparseColumn<T> {
+    pub fn new() -> SparseColumn<T> {
+        SparseColumn {
+            slots: Vec::new(),
+            values: Vec::new(),
+        }
+    }
+
+    /// Where `entity`'s component is in `values`, if it has one.
+    fn position(&self, entity: EntityHandle) -> Option<usize> {
+        let position = (*self.slots.get(entity.index())?)?;
+        if self.values[position].0 == entity {
+            Some(position)
+        } else {
+            None
+        }
+    }
+}
+
+impl<T> Column for SparseColumn<T> {
+    type Item = T;
+    type Iter<'a>
+        = SparseIter<'a, T>
+    where
+        T: 'a;
+    type IterMut<'a>
+        = SparseIterMut<'a, T>
+    where
+        T: 'a;
+
+    fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    fn insert(&mut self, entity: EntityHandle, value: T) -> Option<T> {
+        let index = entity.index();
+        if index >= self.slots.len() {
+            self.slots.resize(index + 1, None);
+        }
+
+        if let Some(position) = self.slots[index] {
+            let (owner, old_value) = std::mem::replace(&mut self.values[position], (entity, value));
+            // Whatever was left behind by a despawned entity is simply overwritten
+            return if owner == entity {
+                Some(old_value)
+            } else {
+                None
+            };
+        }
+
+        self.slots[index] = Some(self.values.len());
+        self.values.push((entity, value));
+        None
+    }
+
+    fn remove(&mut self, entity: EntityHandle) -> Option<T> {
+        let position = self.position(entity)?;
+        self.slots[entity.index()] = None;
+        let (_, value) = self.values.swap_remove(position);
+        if let Some((moved, _)) = self.values.get(position) {
+            self.slots[moved.index()] = Some(position);
+        }
+        Some(value)
+    }
+
+    fn get(&self, entity: EntityHandle) -> Option<&T> {
+        let position = self.position(entity)?;
+        Some(&self.values[position].1)
+    }
+
+    fn get_mut(&mut self, entity: EntityHandle) -> Option<&mut T> {
+        let position = self.position(entity)?;
+        Some(&mut self.values[position].1)
+    }
+
+    fn rebind(&mut self, entities: &Entities) {
+        let mut position = 0;
+        while let Some(&(owner, _)) = self.values.get(position) {
+            match entities.rebind(owner) {
+                Some(rebound) => {
+                    self.values[position].0 = rebound;
+                    position += 1;
+                }
+                // Another component is moved into its place, so check this position again
+                None => {
+                    self.remove(owner);
+                }
+            }
+        }
+    }
+
+    fn iter(&self) -> SparseIter<'_, T> {
+        SparseIter {
+            values: self.values.iter(),
+        }
+    }
+
+    fn iter_mut(&mut self) -> SparseIterMut<'_, T> {
+        SparseIterMut {
+            values: self.values.iter_mut(),
+        }
+    }
+}
+
+/// Iterator over the components in a `SparseColumn`. See `Column::iter`.
+pub struct SparseIter<'a, T> {
+    values: slice::Iter<'a, (EntityHandle, T)>,
+}
+
+impl<'a, T> Iterator for SparseIter<'a, T> {
+    type Item = (EntityHandle, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.values.next().map(|(owner, value)| (*owner, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.values.size_hint()
+    }
+}
+
+/// Iterator over the components in a `SparseColumn`. See `Column::iter_mut`.
+pub struct SparseIterMut<'a, T> {
+    values: slice::IterMut<'a, (EntityHandle, T)>,
+}
+
+impl<'a, T> Iterator for SparseIterMut<'a, T> {
+    type Item = (EntityHandle, &'a mut T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.values.next().map(|(owner, value)| (*owner, value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.values.size_hint()
+    }
+}
+
+/// Return an iterator over every entity which has a component in both `a` and `b`, along with
+/// both components. Entities which were despawned are skipped, even if they left components
+/// behind. This walks through `a` and looks each entity up in `b`, so it's fastest with the
+/// column holding fewer components passed first.
+pub fn join<'a, A: Column, B: Column>(
+    entities: &'a Entities,
+    a: &'a A,
+    b: &'a B,
+) -> impl Iterator<Item = (EntityHandle, &'a A::Item, &'a B::Item)> + 'a {
+    a.iter().filter_map(move |(entity, a_value)| {
+        if !entities.contains(entity) {
+            return None;
+        }
+        Some((entity, a_value, b.get(entity)?))
+    })
+}
+
+/// Like `join`, but with mutable access to the components in `a`.
+pub fn join_mut<'a, A: Column, B: Column>(
+    entities: &'a Entities,
+    a: &'a mut A,
+    b: &'a B,
+) -> impl Iterator<Item = (EntityHandle, &'a mut A::Item, &'a B::Item)> + 'a {
+    a.iter_mut().filter_map(move |(entity, a_value)| {
+        if !entities.contains(entity) {
+            return None;
+        }
+        Some((entity, a_value, b.get(entity)?))
+    })
+}
+
+/// Like `join`, but for entities with a component in each of three columns.
+pub fn join3<'a, A: Column, B: Column, C: Column>(
+    entities: &'a Entities,
+    a: &'a A,
+    b: &'a B,
+    c: &'a C,
+) -> impl Iterator<Item = (EntityHandle, &'a A::Item, &'a B::Item, &'a C::Item)> + 'a {
+    a.iter().filter_map(move |(entity, a_value)| {
+        if !entities.contains(entity) {
+            return None;
+        }
+        Some((entity, a_value, b.get(entity)?, c.get(entity)?))
+    })
+}
+
+/// Like `join3`, but with mutable access to the components in `a`.
+pub fn join3_mut<'a, A: Column, B: Column, C: Column>(
+    entities: &'a Entities,
+    a: &'a mut A,
+    b: &'a B,
+    c: &'a C,
+) -> impl Iterator<Item = (EntityHandle, &'a mut A::Item, &'a B::Item, &'a C::Item)> + 'a {
+    a.iter_mut().filter_map(move |(entity, a_value)| {
+        if !entities.contains(entity) {
+            return None;
+        }
+        Some((entity, a_value, b.get(entity)?, c.get(entity)?))
+    })
+}
@@ -7,6 +7,7 @@ use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
 /// Note: This struct is so small you should prefer passing it around by copy rather than by
 /// reference. See also: https://rust-lang-nursery.github.io/rust-clippy/v0.0.212/index.html#trivially_copy_pass_by_ref
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct P2 {
     pub x: f32,
     pub y: f32,
@@ -24,6 +25,7 @@ pub struct P2 {
 /// Note: This struct is so small you should prefer passing it around by copy rather than by
 /// reference. See also: https://rust-lang-nursery.github.io/rust-clippy/v0.0.212/index.html#trivially_copy_pass_by_ref
 #[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct V2 {
     pub x: f32,
     pub y: f32,
@@ -9,6 +9,7 @@ use std::{
 ///
 /// Note: You can convert a `(x, y)` tuple into a grid using `into()`
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GridCoord {
     pub x: i16,
     pub y: i16,
@@ -20,6 +21,7 @@ pub struct GridCoord {
 ///
 /// Note: You can convert a `(x, y)` tuple into a range using `into()`
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GridRange {
     pub w: u16,
     pub h: u16,
@@ -32,6 +34,7 @@ pub struct GridRange {
 /// a range of (2, 4) indicates the region that stretches from `(x, y)` to `(x+2, y+4)`. A region
 /// can never be smaller than a single square.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GridRegion {
     pub coord: GridCoord,
     pub range: GridRange,
@@ -140,6 +143,7 @@ impl GridRegion {
 /// This can be useful, for example, to a system responsible for managing collisions - it can
 /// partition the world up into subsections, registering bodies with much smaller areas, so that
 /// when it runs a pass to test collisions, it can vastly reduce the number of bodies to consider.
+#[derive(Clone)]
 pub struct Grid<T: Copy + Eq + Hash> {
     coord_items: HashMap<GridCoord, HashSet<T>>,
     item_regions: HashMap<T, GridRegion>,
@@ -187,6 +191,11 @@ impl<T: Copy + Eq + Hash> Grid<T> {
         }
     }
 
+    /// Return an iterator over every item in the grid, along with the region it covers.
+    pub fn regions(&self) -> impl Iterator<Item = (&T, &GridRegion)> {
+        self.item_regions.iter()
+    }
+
     pub fn query(&self, region: GridRegion) -> HashSet<&T> {
         HashSet::from_iter(
             region
@@ -195,6 +204,47 @@ impl<T: Copy + Eq + Hash> Grid<T> {
                 .flatten(),
         )
     }
+
+    /// Like `query`, but put the items into `items` (replacing whatever was there), sorted and
+    /// without duplicates. Passing in the same `items` every time saves allocating a new set for
+    /// every query.
+    pub fn query_into(&self, region: GridRegion, items: &mut Vec<T>)
+    where
+        T: Ord,
+    {
+        items.clear();
+        items.extend(
+            region
+                .iter()
+                .filter_map(|coord| self.coord_items.get(&coord))
+                .flatten(),
+        );
+        items.sort_unstable();
+        items.dedup();
+    }
+}
+
+/// Only the region each item covers is serialized, as the items in each square follow from that.
+#[cfg(feature = "serde")]
+impl<T: Copy + Eq + Hash + Ord + serde::Serialize> serde::Serialize for Grid<T> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Sorted, so the same grid always serializes the same way
+        let mut item_regions: Vec<(&T, &GridRegion)> = self.item_regions.iter().collect();
+        item_regions.sort_by_key(|&(item, _)| item);
+        serializer.collect_seq(item_regions)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de, T: Copy + Eq + Hash + serde::Deserialize<'de>> serde::Deserialize<'de> for Grid<T> {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let item_regions: Vec<(T, GridRegion)> = serde::Deserialize::deserialize(deserializer)?;
+        let mut grid = Grid::new();
+        for (item, region) in item_regions {
+            grid.insert(item, region);
+        }
+        Ok(grid)
+    }
 }
 
 #[cfg(test)]
@@ -2,6 +2,7 @@
 //! development of 2D games.
 
 pub mod collide;
+pub mod components;
 pub mod geom;
 pub mod ggez;
 pub mod grid;
@@ -1,15 +1,30 @@
+use std::cmp::Ordering;
+use std::fmt;
 use std::hash::Hash;
 use std::hash::Hasher;
+use std::marker::PhantomData;
 use std::mem;
+use std::sync::atomic::{AtomicU32, Ordering as AtomicOrdering};
 
-const MATCH_ALL_IDS: u32 = 0;
-const FIRST_VALID_ID: u32 = 1;
+/// Every pool gets its own ID, so handles can tell which pool they came from. IDs start at 1, so
+/// no pool ever has an ID of 0.
+static NEXT_POOL_ID: AtomicU32 = AtomicU32::new(1);
 
+fn next_pool_id() -> u32 {
+    NEXT_POOL_ID.fetch_add(1, AtomicOrdering::Relaxed)
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 enum Entry<T> {
-    /// `usize` parameter indicates next free slot
-    Free(usize),
-    /// `usize` parameter used for unique ID
+    /// `usize` parameter indicates next free slot, and `u32` the generation the next value stored
+    /// here will have
+    Free(usize, u32),
+    /// `u32` parameter is the value's generation, which is bumped every time the slot is reused
     Value(u32, T),
+    /// A slot whose generation ran out, and so can never be reused without old handles to it
+    /// becoming valid again. It stays empty forever instead.
+    Retired,
 }
 
 /// A `Pool` is pre-allocated array that can be used for managing a collection of objects. Unlike a
@@ -17,35 +32,126 @@ enum Entry<T> {
 /// middle of the list, it is marked re-usable - all previously allocated objects don't move, and
 /// the next allocation request will be given that spot.
 ///
-/// Don't use a pool if you want a collection that can shrink / reclaim memory over time, or if you
-/// need the insertion order to matter.
+/// Don't use a pool if you need the insertion order to matter. A pool only gives memory back when
+/// asked to, with `shrink_to_fit` or `compact`.
+///
+/// Each slot counts how many times it has been reused (its generation), and a handle only matches
+/// the generation it was created with, so stale handles are always caught. After a slot has been
+/// reused `u32::MAX` times, it's retired for good, instead of letting its generation wrap around.
+///
+/// Cloned and deserialized pools are new pools, with the same objects in the same slots. Handles
+/// from the pool they were copied from have to go through `rebind` before they work with them.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Pool<T> {
     entries: Vec<Entry<T>>,
     next_free: usize,
     len: usize,
-    next_id: u32,
+    /// The generation that slots start out at when the pool grows. Slots cut off by
+    /// `shrink_to_fit` might come back later, and this keeps handles to what they used to hold
+    /// from matching what they hold next.
+    min_generation: u32,
+    /// Which pool this is. Never saved, as IDs only mean something within a single run.
+    #[cfg_attr(feature = "serde", serde(skip, default = "next_pool_id"))]
+    pool_id: u32,
 }
 
 /// A handle will be returned to the caller by the pool when they add a new object, and it can then
 /// be used to safely query / remove the object later.
-#[derive(Debug, Clone, Copy)]
-pub struct Handle {
-    index: usize,
-    /// ID which verifies that the entry we fetched by this handle is actually the one the handle
-    /// was originally associated with (vs. the old entry being removed and a new entry being
-    /// allocated into its spot later).
-    entry_id: u32,
+///
+/// Handles are typed by what the pool holds, so one from a `Pool<A>` can't be used with a
+/// `Pool<B>`. Using one with a different pool of the same type is caught at runtime instead; the
+/// other pool acts as if the handle's object was already removed.
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(bound = ""))]
+pub struct Handle<T> {
+    index: u32,
+    /// Generation which verifies that the entry we fetched by this handle is actually the one the
+    /// handle was originally associated with (vs. the old entry being removed and a new entry
+    /// being allocated into its spot later).
+    generation: u32,
+    /// ID of the pool which created this handle. Never saved, so deserialized handles have an ID
+    /// of 0 and don't belong to any pool until they're passed through `Pool::rebind`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pool_id: u32,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Handle<T> {
+    fn new(index: usize, generation: u32, pool_id: u32) -> Handle<T> {
+        Handle {
+            index: index as u32,
+            generation,
+            pool_id,
+            _marker: PhantomData,
+        }
+    }
+
+    /// The slot this handle points at. Slots are never moved around (short of `Pool::compact`),
+    /// so this can be used to key other storage alongside the pool.
+    pub(crate) fn index(&self) -> usize {
+        self.index as usize
+    }
+}
+
+// Implemented by hand, as deriving these would require `T` to implement them too
+impl<T> Clone for Handle<T> {
+    fn clone(&self) -> Handle<T> {
+        *self
+    }
+}
+impl<T> Copy for Handle<T> {}
+impl<T> fmt::Debug for Handle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Handle")
+            .field("index", &self.index)
+            .field("generation", &self.generation)
+            .field("pool_id", &self.pool_id)
+            .finish()
+    }
 }
 
-impl Eq for Handle {}
-impl PartialEq<Handle> for Handle {
-    fn eq(&self, other: &Handle) -> bool {
-        self.entry_id.eq(&other.entry_id) // ID alone guarantees equality
+impl<T> Eq for Handle<T> {}
+impl<T> PartialEq<Handle<T>> for Handle<T> {
+    fn eq(&self, other: &Handle<T>) -> bool {
+        (self.index, self.generation, self.pool_id)
+            == (other.index, other.generation, other.pool_id)
     }
 }
-impl Hash for Handle {
+/// The pool ID is left out of the hash, so hashes of handles don't depend on which pool they came
+/// from, only on what was done to it.
+impl<T> Hash for Handle<T> {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        state.write_u32(self.entry_id); // ID alone guarantees uniqueness
+        state.write_u32(self.index);
+        state.write_u32(self.generation);
+    }
+}
+/// Handles are ordered by the slot they point at, which gives collections of them a stable order
+/// that doesn't depend on anything but what was done to the pool.
+impl<T> Ord for Handle<T> {
+    fn cmp(&self, other: &Handle<T>) -> Ordering {
+        (self.index, self.generation, self.pool_id).cmp(&(
+            other.index,
+            other.generation,
+            other.pool_id,
+        ))
+    }
+}
+impl<T> PartialOrd for Handle<T> {
+    fn partial_cmp(&self, other: &Handle<T>) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Clone> Clone for Pool<T> {
+    fn clone(&self) -> Pool<T> {
+        Pool {
+            entries: self.entries.clone(),
+            next_free: self.next_free,
+            len: self.len,
+            min_generation: self.min_generation,
+            pool_id: next_pool_id(),
+        }
     }
 }
 
@@ -55,7 +161,7 @@ impl<T> Entry<T> {
     #[inline]
     pub fn is_free(&self) -> bool {
         match *self {
-            Entry::Free(_) => true,
+            Entry::Free(..) => true,
             _ => false,
         }
     }
@@ -63,16 +169,17 @@ impl<T> Entry<T> {
     /// Returns `true` if this is an `Entry::Value`, indicating this slot contains a value.
     #[inline]
     pub fn has_value(&self) -> bool {
-        !self.is_free()
+        self.generation().is_some()
     }
 
-    /// Returns the ID of this entry, if this is an `Entry::Value`.
+    /// Returns the generation of this entry, if this is an `Entry::Value`.
     ///
-    /// The ID helps protect access from stale `Handle`s that points to an index since recycled.
+    /// The generation helps protect access from stale `Handle`s that points to an index since
+    /// recycled.
     #[inline]
-    pub fn id(&self) -> Option<u32> {
-        if let Entry::Value(id, _) = self {
-            return Some(*id);
+    pub fn generation(&self) -> Option<u32> {
+        if let Entry::Value(generation, _) = self {
+            return Some(*generation);
         }
         None
     }
@@ -80,37 +187,39 @@ impl<T> Entry<T> {
     /// Return the wrapped value of this entry, if this is an `Entry::Value`
     #[inline]
     pub fn value(&self) -> Option<&T> {
-        self.value_with_id(MATCH_ALL_IDS)
+        if let Entry::Value(_, value) = self {
+            return Some(value);
+        }
+        None
     }
 
     /// Mutable version of `value`
     #[inline]
     pub fn value_mut(&mut self) -> Option<&mut T> {
-        self.value_with_id_mut(MATCH_ALL_IDS)
+        if let Entry::Value(_, value) = self {
+            return Some(value);
+        }
+        None
     }
 
-    /// Return the wrapped value of this entry, if this is an `Entry::Value` and if `id` matches the
-    /// entry's ID. This method can help protect access from stale `Handle`s that points to an
-    /// index since recycled.
+    /// Return the wrapped value of this entry, if this is an `Entry::Value` and if `generation`
+    /// matches the entry's generation. This method can help protect access from stale `Handle`s
+    /// that points to an index since recycled.
     #[inline]
-    pub fn value_with_id(&self, id: u32) -> Option<&T> {
-        if let Entry::Value(value_id, value) = self {
-            if id == MATCH_ALL_IDS || *value_id == id {
-                return Some(&*value);
-            }
+    pub fn value_with_generation(&self, generation: u32) -> Option<&T> {
+        match self {
+            Entry::Value(value_generation, value) if *value_generation == generation => Some(value),
+            _ => None,
         }
-        None
     }
 
-    /// Mutable version of `value_with_id`
+    /// Mutable version of `value_with_generation`
     #[inline]
-    pub fn value_with_id_mut(&mut self, id: u32) -> Option<&mut T> {
-        if let Entry::Value(value_id, value) = self {
-            if id == MATCH_ALL_IDS || *value_id == id {
-                return Some(&mut *value);
-            }
+    pub fn value_with_generation_mut(&mut self, generation: u32) -> Option<&mut T> {
+        match self {
+            Entry::Value(value_generation, value) if *value_generation == generation => Some(value),
+            _ => None,
         }
-        None
     }
 }
 
@@ -129,20 +238,21 @@ impl<T> Pool<T> {
         }
 
         let mut entries = Vec::with_capacity(capacity);
-        Pool::fill_with_free_entries(&mut entries);
+        Pool::fill_with_free_entries(&mut entries, 0);
         Pool {
             entries,
             next_free: 0,
             len: 0,
-            next_id: FIRST_VALID_ID,
+            min_generation: 0,
+            pool_id: next_pool_id(),
         }
     }
 
     /// Helper function that initializes the `entries` array with `Free` items pointing at the next
     /// free slot.
-    fn fill_with_free_entries(entries: &mut Vec<Entry<T>>) {
+    fn fill_with_free_entries(entries: &mut Vec<Entry<T>>, generation: u32) {
         for i in entries.len()..entries.capacity() {
-            entries.push(Entry::Free(i + 1))
+            entries.push(Entry::Free(i + 1, generation))
         }
     }
 
@@ -165,45 +275,51 @@ impl<T> Pool<T> {
     }
 
     /// Add a new object to the next open free slot in this pool.
-    pub fn push(&mut self, value: T) -> Handle {
-        if self.len == self.entries.capacity() {
-            self.entries.reserve(self.len * 2);
-            Pool::fill_with_free_entries(&mut self.entries);
+    pub fn push(&mut self, value: T) -> Handle<T> {
+        // The last free slot always points one past the end, so running out looks like this
+        if self.next_free == self.entries.len() {
+            // A compacted pool may have no slots at all
+            self.entries.reserve(self.entries.len().max(1) * 2);
+            Pool::fill_with_free_entries(&mut self.entries, self.min_generation);
         }
 
-        let next_id = self.next_id;
-        self.next_id += 1;
-        self.len += 1;
-
-        let handle = Handle {
-            index: self.next_free,
-            entry_id: next_id,
-        };
-
-        let free_entry = mem::replace(
-            &mut self.entries[self.next_free],
-            Entry::Value(next_id, value),
-        );
-        if let Entry::Free(next_free) = free_entry {
-            self.next_free = next_free;
-        } else {
-            panic!("Unexpected pool state: self.next_free pointed to non-free slot")
+        let index = self.next_free;
+        if index > u32::MAX as usize {
+            panic!("Pool is full")
         }
+        let generation = match self.entries[index] {
+            Entry::Free(next_free, generation) => {
+                self.next_free = next_free;
+                generation
+            }
+            _ => panic!("Unexpected pool state: self.next_free pointed to non-free slot"),
+        };
+        self.entries[index] = Entry::Value(generation, value);
+        self.len += 1;
 
-        handle
+        Handle::new(index, generation, self.pool_id)
     }
 
     /// Remove an object by its handle. This will return `None` if the object allocated for that
     /// handle was already removed.
-    pub fn remove(&mut self, handle: Handle) -> Option<T> {
+    pub fn remove(&mut self, handle: Handle<T>) -> Option<T> {
         // If the entry is already removed OR if a new one was reallocated in its place from the
         // object referenced by the handle, then reject this request to remove, returning None.
-        self.entries[handle.index].value_with_id(handle.entry_id)?;
+        self.get(handle)?;
+
+        let index = handle.index as usize;
+        // Slots which run out of generations are retired, rather than going back on the free list
+        let freed = match handle.generation.checked_add(1) {
+            Some(generation) => Entry::Free(self.next_free, generation),
+            None => Entry::Retired,
+        };
+        if freed.is_free() {
+            self.next_free = index;
+        }
 
-        let removed = mem::replace(&mut self.entries[handle.index], Entry::Free(self.next_free));
+        let removed = mem::replace(&mut self.entries[index], freed);
         if let Entry::Value(_, value) = removed {
             self.len -= 1;
-            self.next_free = handle.index;
             return Some(value);
         } else {
             panic!("Unexpected pool state: removed entry should always be a Value");
@@ -211,14 +327,24 @@ impl<T> Pool<T> {
     }
 
     /// Query an object by its handle. This will return `None` if the object allocated for that
-    /// handle was already removed.
-    pub fn get(&self, handle: Handle) -> Option<&T> {
-        self.entries[handle.index].value_with_id(handle.entry_id)
+    /// handle was already removed, or if the handle came from another pool.
+    pub fn get(&self, handle: Handle<T>) -> Option<&T> {
+        if handle.pool_id != self.pool_id {
+            return None;
+        }
+        self.entries
+            .get(handle.index as usize)?
+            .value_with_generation(handle.generation)
     }
 
     /// Mutable version of `get`.
-    pub fn get_mut(&mut self, handle: Handle) -> Option<&mut T> {
-        self.entries[handle.index].value_with_id_mut(handle.entry_id)
+    pub fn get_mut(&mut self, handle: Handle<T>) -> Option<&mut T> {
+        if handle.pool_id != self.pool_id {
+            return None;
+        }
+        self.entries
+            .get_mut(handle.index as usize)?
+            .value_with_generation_mut(handle.generation)
     }
 
     /// Return an iterator that provides access to all entries in this pool. The order is not
@@ -238,22 +364,191 @@ impl<T> Pool<T> {
             .map(|entry| entry.value_mut().unwrap())
     }
 
+    /// Like `iter`, but along with the handle to each entry.
+    pub fn iter_with_handles(&self) -> impl Iterator<Item = (Handle<T>, &T)> {
+        let pool_id = self.pool_id;
+        self.entries
+            .iter()
+            .enumerate()
+            .filter_map(move |(i, entry)| match entry {
+                Entry::Value(generation, value) => {
+                    Some((Handle::new(i, *generation, pool_id), value))
+                }
+                _ => None,
+            })
+    }
+
+    /// Mutable version of `iter_with_handles`
+    pub fn iter_mut_with_handles(&mut self) -> impl Iterator<Item = (Handle<T>, &mut T)> {
+        let pool_id = self.pool_id;
+        self.entries
+            .iter_mut()
+            .enumerate()
+            .filter_map(move |(i, entry)| match entry {
+                Entry::Value(generation, value) => {
+                    Some((Handle::new(i, *generation, pool_id), value))
+                }
+                _ => None,
+            })
+    }
+
+    /// Returns `true` if `handle` still points to an object in this pool.
+    pub fn contains(&self, handle: Handle<T>) -> bool {
+        self.get(handle).is_some()
+    }
+
+    /// Remove every object for which `f` returns `false`, e.g. to clear out everything that died
+    /// this frame.
+    pub fn retain<F: FnMut(Handle<T>, &T) -> bool>(&mut self, mut f: F) {
+        for index in 0..self.entries.len() {
+            let handle = match &self.entries[index] {
+                Entry::Value(generation, value) => {
+                    let handle = Handle::new(index, *generation, self.pool_id);
+                    if f(handle, value) {
+                        continue;
+                    }
+                    handle
+                }
+                _ => continue,
+            };
+            self.remove(handle);
+        }
+    }
+
+    /// Remove every object from this pool, returning them along with the handles they had.
+    pub fn drain(&mut self) -> impl Iterator<Item = (Handle<T>, T)> {
+        let mut drained = Vec::with_capacity(self.len);
+        for index in 0..self.entries.len() {
+            if let Some(generation) = self.entries[index].generation() {
+                let handle = Handle::new(index, generation, self.pool_id);
+                drained.extend(self.remove(handle).map(|value| (handle, value)));
+            }
+        }
+        drained.into_iter()
+    }
+
+    /// Remove every object from this pool. Handles to them are no longer valid afterwards, even
+    /// once their slots are reused.
+    pub fn clear(&mut self) {
+        self.retain(|_, _| false);
+    }
+
+    /// Give back the memory used by free slots at the end of the pool. Handles to every object in
+    /// the pool stay valid, so any free slots in between objects are left alone; see `compact`
+    /// for getting rid of those as well.
+    pub fn shrink_to_fit(&mut self) {
+        let keep = self
+            .entries
+            .iter()
+            .rposition(|entry| !entry.is_free())
+            .map_or(0, |index| index + 1);
+        for entry in self.entries[keep..].iter() {
+            if let Entry::Free(_, generation) = *entry {
+                self.min_generation = self.min_generation.max(generation);
+            }
+        }
+
+        self.entries.truncate(keep);
+        self.entries.shrink_to_fit();
+        Pool::fill_with_free_entries(&mut self.entries, self.min_generation);
+        self.relink_free_entries();
+    }
+
+    /// Move every object as close to the front of the pool as possible, and then `shrink_to_fit`.
+    /// This gives back as much memory as possible, but moved objects get new handles, and their
+    /// old handles become invalid. `remap` is called with the old and new handle of each one, so
+    /// anything referring to them can be fixed up. Objects are kept in the same order.
+    pub fn compact<F: FnMut(Handle<T>, Handle<T>)>(&mut self, mut remap: F) {
+        let mut dest = 0;
+        for index in 0..self.entries.len() {
+            let generation = match self.entries[index].generation() {
+                Some(generation) => generation,
+                None => continue,
+            };
+            while dest < index && !self.entries[dest].is_free() {
+                dest += 1;
+            }
+            if dest == index {
+                dest += 1;
+                continue;
+            }
+
+            // Vacate the old slot the same way `remove` would, so handles to it are rejected
+            let vacated = match generation.checked_add(1) {
+                Some(generation) => Entry::Free(0, generation),
+                None => Entry::Retired,
+            };
+            let value = match mem::replace(&mut self.entries[index], vacated) {
+                Entry::Value(_, value) => value,
+                _ => panic!("Unexpected pool state: moved entry should always be a Value"),
+            };
+            let dest_generation = match self.entries[dest] {
+                Entry::Free(_, generation) => generation,
+                _ => panic!("Unexpected pool state: compacted into a non-free slot"),
+            };
+            self.entries[dest] = Entry::Value(dest_generation, value);
+
+            remap(
+                Handle::new(index, generation, self.pool_id),
+                Handle::new(dest, dest_generation, self.pool_id),
+            );
+            dest += 1;
+        }
+
+        self.shrink_to_fit();
+    }
+
+    /// Rebuild the list of free slots from scratch, lowest index first, for after slots were
+    /// shuffled around without keeping it up to date.
+    fn relink_free_entries(&mut self) {
+        let mut next_free = self.entries.len();
+        for (index, entry) in self.entries.iter_mut().enumerate().rev() {
+            if let Entry::Free(next, _) = entry {
+                *next = next_free;
+                next_free = index;
+            }
+        }
+        self.next_free = next_free;
+    }
+
+    /// Give this pool the ID of `other`, so that it accepts `other`'s handles instead of its own,
+    /// e.g. to put `other` back how it was from a copy of it. `other` should be thrown away
+    /// afterwards, or the two pools will accept each other's handles.
+    pub(crate) fn take_id(&mut self, other: &Pool<T>) {
+        self.pool_id = other.pool_id;
+    }
+
+    /// Return `handle` as if it came from this pool, whether or not it points to anything here.
+    pub(crate) fn adopt(&self, handle: Handle<T>) -> Handle<T> {
+        Handle::new(handle.index(), handle.generation, self.pool_id)
+    }
+
+    /// Turn a handle from a copy of this pool (or one that was saved along with it and loaded
+    /// back in) into one that works with this pool. This will return `None` unless this pool has
+    /// an object in the handle's slot, with the same generation.
+    pub fn rebind(&self, handle: Handle<T>) -> Option<Handle<T>> {
+        let handle = self.adopt(handle);
+        if self.contains(handle) {
+            Some(handle)
+        } else {
+            None
+        }
+    }
+
     /// Return a newly collection of all handles that currently point to valid entries in this pool. It
     /// can be useful to call this instead of `iter` or `iter_mut` since those methods keep a
-    /// reference to the pool while this call does not.
-    pub fn handles(&self) -> impl Iterator<Item = Handle> {
+    /// reference to the pool while this call does not; use `iter_with_handles` if you don't need
+    /// that, which avoids allocating.
+    pub fn handles(&self) -> impl Iterator<Item = Handle<T>> {
         self.entries
             .iter()
             .enumerate() // We need the index to create handles
             .filter(|(_i, entry)| entry.has_value())
-            .map(|(i, entry)| Handle {
-                index: i,
-                entry_id: entry.id().unwrap(),
-            })
+            .map(|(i, entry)| Handle::new(i, entry.generation().unwrap(), self.pool_id))
             // Up to this point, the iterator keeps a reference to self.entries. We want to break
             // that link, so we do it by creating a new vector and returning that as an iterator.
             // (There may be a better way to do this but it seems to work for now!)
-            .collect::<Vec<Handle>>()
+            .collect::<Vec<Handle<T>>>()
             .into_iter()
     }
 }
@@ -264,25 +559,54 @@ mod tests {
 
     #[test]
     fn entry_impl_methods_work() {
-        let mut free_entry: Entry<&str> = Entry::Free(20);
+        let mut free_entry: Entry<&str> = Entry::Free(20, 5);
         let mut value_entry: Entry<&str> = Entry::Value(5, "test");
 
         assert_eq!(free_entry.is_free(), true);
         assert_eq!(free_entry.has_value(), false);
         assert_eq!(free_entry.value().is_none(), true);
         assert_eq!(free_entry.value_mut().is_none(), true);
-        assert_eq!(free_entry.value_with_id(1).is_none(), true);
-        assert_eq!(free_entry.value_with_id_mut(1).is_none(), true);
-        assert_eq!(free_entry.value_with_id(5).is_none(), true);
-        assert_eq!(free_entry.value_with_id_mut(5).is_none(), true);
+        assert_eq!(free_entry.value_with_generation(1).is_none(), true);
+        assert_eq!(free_entry.value_with_generation_mut(1).is_none(), true);
+        assert_eq!(free_entry.value_with_generation(5).is_none(), true);
+        assert_eq!(free_entry.value_with_generation_mut(5).is_none(), true);
 
         assert_eq!(value_entry.is_free(), false);
         assert_eq!(value_entry.has_value(), true);
         assert_eq!(value_entry.value().unwrap(), &"test");
         assert_eq!(value_entry.value_mut().unwrap(), &"test");
-        assert_eq!(value_entry.value_with_id(1).is_none(), true);
-        assert_eq!(value_entry.value_with_id_mut(1).is_none(), true);
-        assert_eq!(value_entry.value_with_id(5).unwrap(), &"test");
-        assert_eq!(value_entry.value_with_id_mut(5).unwrap(), &"test");
+        assert_eq!(value_entry.value_with_generation(1).is_none(), true);
+        assert_eq!(value_entry.value_with_generation_mut(1).is_none(), true);
+        assert_eq!(value_entry.value_with_generation(5).unwrap(), &"test");
+        assert_eq!(value_entry.value_with_generation_mut(5).unwrap(), &"test");
+        assert_eq!(value_entry.generation(), Some(5));
+
+        let retired_entry: Entry<&str> = Entry::Retired;
+        assert_eq!(retired_entry.is_free(), false);
+        assert_eq!(retired_entry.has_value(), false);
+        assert_eq!(retired_entry.value().is_none(), true);
+        assert_eq!(retired_entry.generation(), None);
+    }
+
+    #[test]
+    fn slots_are_retired_when_their_generations_run_out() {
+        let mut pool: Pool<&str> = Pool::with_capacity(2);
+        pool.push("first");
+        // Pretend the first slot has been reused as many times as it can be
+        pool.entries[0] = Entry::Value(u32::MAX, "last");
+        let handle_last = Handle::new(0, u32::MAX, pool.pool_id);
+        assert_eq!(pool.get(handle_last), Some(&"last"));
+
+        assert_eq!(pool.remove(handle_last), Some("last"));
+        assert_eq!(pool.is_empty(), true);
+        assert_eq!(pool.entries[0].is_free(), false);
+        assert_eq!(pool.get(handle_last), None);
+        assert_eq!(pool.get(Handle::new(0, 0, pool.pool_id)), None);
+
+        // New objects go everywhere but the retired slot
+        let handles: Vec<_> = (0..4).map(|_| pool.push("new")).collect();
+        assert_eq!(handles.iter().any(|handle| handle.index == 0), false);
+        assert_eq!(pool.len(), 4);
+        assert_eq!(pool.iter().count(), 4);
     }
 }
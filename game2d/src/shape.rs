@@ -1,5 +1,13 @@
 use crate::geom::{P2, V2};
+use std::ops::BitOr;
 
+/// How far two rects may already be overlapping at the start of a sweep and still be considered
+/// touching. Without some slack, floating point error can leave a body sitting a hair inside a
+/// wall, at which point it would be allowed to pass straight through it.
+const SWEEP_EPSILON: f32 = 0.001;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum RectSide {
     Top,
     Bottom,
@@ -7,12 +15,115 @@ pub enum RectSide {
     Right,
 }
 
-#[derive(Copy, Clone, Debug)]
+impl RectSide {
+    /// A unit vector pointing outwards from this side of a rect.
+    pub fn normal(self) -> V2 {
+        match self {
+            RectSide::Top => V2::new(0., -1.),
+            RectSide::Bottom => V2::new(0., 1.),
+            RectSide::Left => V2::new(-1., 0.),
+            RectSide::Right => V2::new(1., 0.),
+        }
+    }
+}
+
+/// A set of `RectSide`s. Combine sides with `|`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct RectSides(u8);
+
+impl RectSides {
+    pub const NONE: RectSides = RectSides(0);
+    pub const TOP: RectSides = RectSides(1);
+    pub const BOTTOM: RectSides = RectSides(1 << 1);
+    pub const LEFT: RectSides = RectSides(1 << 2);
+    pub const RIGHT: RectSides = RectSides(1 << 3);
+    pub const ALL: RectSides = RectSides(0b1111);
+
+    pub fn contains(self, side: RectSide) -> bool {
+        self.0 & RectSides::from(side).0 != 0
+    }
+
+    /// Returns every side that isn't in this set.
+    pub fn inverse(self) -> RectSides {
+        RectSides(!self.0 & RectSides::ALL.0)
+    }
+}
+
+impl From<RectSide> for RectSides {
+    fn from(side: RectSide) -> Self {
+        match side {
+            RectSide::Top => RectSides::TOP,
+            RectSide::Bottom => RectSides::BOTTOM,
+            RectSide::Left => RectSides::LEFT,
+            RectSide::Right => RectSides::RIGHT,
+        }
+    }
+}
+
+impl BitOr for RectSides {
+    type Output = RectSides;
+
+    fn bitor(self, rhs: RectSides) -> RectSides {
+        RectSides(self.0 | rhs.0)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Rect {
     pub pos: P2,
     pub size: V2,
 }
 
+/// The outline of a collision body, which always fits snugly inside its bounding rect.
+///
+/// All shapes are stored as a bounding rect plus one of these values, which means that shapes
+/// other than `Shape::Rect` can always fall back to their bounding rect for cheap, approximate
+/// checks (such as figuring out which grid cells they belong to).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Shape {
+    /// Fills the entire bounding rect.
+    Rect,
+    /// A circle, centered in the bounding rect, whose diameter is the shorter of the rect's sides.
+    Circle,
+    /// A rect whose two short ends are fully rounded off, e.g. a pill shape. If the bounding rect
+    /// is a square, this is identical to `Shape::Circle`.
+    Capsule,
+}
+
+/// A line which starts at some `origin` and extends out forever in some direction.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: P2,
+    /// A unit vector indicating the direction of this ray.
+    pub dir: V2,
+}
+
+/// The result of casting a `Ray` against some target. See `Ray::intersect_rect` for more details.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RayIntersection {
+    /// The distance along the ray to the point it intersected the target at.
+    pub distance: f32,
+    /// Where the ray first intersected the target.
+    pub point: P2,
+    /// A unit vector pointing out of the side of the target that the ray hit.
+    pub normal: V2,
+}
+
+/// The result of sweeping one rect into another. See `Rect::sweep` for more details.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SweepHit {
+    /// How far along the sweep the contact happened, from 0.0 (immediately) to 1.0 (at the very
+    /// end of the movement).
+    pub time: f32,
+    /// A unit vector pointing out of the side of the rect that was hit.
+    pub normal: V2,
+    /// The side of the target rect that the moving rect ran into.
+    pub side: RectSide,
+}
+
 impl Rect {
     pub fn new(pos: P2, size: V2) -> Rect {
         Rect { pos, size }
@@ -38,6 +149,13 @@ impl Rect {
         self.top() + self.size.y
     }
 
+    /// Returns `true` if `pt` is inside this rect. Points on the top and left edges are considered
+    /// inside, while points on the bottom and right edges are not, so that a point exactly between
+    /// two adjacent rects only belongs to one of them.
+    pub fn contains(&self, pt: P2) -> bool {
+        pt.x >= self.left() && pt.x < self.right() && pt.y >= self.top() && pt.y < self.bottom()
+    }
+
     pub fn overlaps(&self, other: &Rect) -> bool {
         !(self.right() <= other.left()
             || self.left() >= other.right()
@@ -70,4 +188,366 @@ impl Rect {
             RectSide::Top
         }
     }
+
+    /// Returns the side of this rect that `other` is closest to, which is the side it's touching
+    /// if the two rects are touching. If `other` overlaps this rect, the side it could escape
+    /// through the fastest is returned instead.
+    pub fn closest_side(&self, other: &Rect) -> RectSide {
+        let overlap_x = self.right().min(other.right()) - self.left().max(other.left());
+        let overlap_y = self.bottom().min(other.bottom()) - self.top().max(other.top());
+        let self_center = self.pos + self.size / 2.;
+        let other_center = other.pos + other.size / 2.;
+
+        if overlap_x < overlap_y {
+            if other_center.x < self_center.x {
+                RectSide::Left
+            } else {
+                RectSide::Right
+            }
+        } else if other_center.y < self_center.y {
+            RectSide::Top
+        } else {
+            RectSide::Bottom
+        }
+    }
+
+    /// Move this rect along `delta` and report the earliest point at which it would run into
+    /// `target`, or `None` if it would slide past it completely.
+    ///
+    /// Unlike `overlaps`, this doesn't only check the final position, so even a very fast moving
+    /// rect will never skip over a thin target. Rects which start out overlapping are not reported,
+    /// allowing them to separate from each other, although a rect which starts out exactly
+    /// touching `target` and moves into it will be reported with a `time` of 0.
+    pub fn sweep(&self, delta: V2, target: &Rect) -> Option<SweepHit> {
+        if delta.is_zero() {
+            return None;
+        }
+
+        let (entry_x, exit_x) = sweep_axis(
+            self.left(),
+            self.right(),
+            target.left(),
+            target.right(),
+            delta.x,
+        )?;
+        let (entry_y, exit_y) = sweep_axis(
+            self.top(),
+            self.bottom(),
+            target.top(),
+            target.bottom(),
+            delta.y,
+        )?;
+
+        let hit_x_first = entry_x > entry_y;
+        let (entry, entry_speed) = if hit_x_first {
+            (entry_x, delta.x.abs())
+        } else {
+            (entry_y, delta.y.abs())
+        };
+        let exit = exit_x.min(exit_y);
+
+        if entry >= exit || entry >= 1. || exit <= 0. {
+            return None;
+        }
+        if entry < 0. && -entry * entry_speed > SWEEP_EPSILON {
+            return None; // Already overlapping significantly before we even started
+        }
+
+        let side = if hit_x_first {
+            if delta.x > 0. {
+                RectSide::Left
+            } else {
+                RectSide::Right
+            }
+        } else if delta.y > 0. {
+            RectSide::Top
+        } else {
+            RectSide::Bottom
+        };
+
+        Some(SweepHit {
+            time: entry.max(0.),
+            normal: side.normal(),
+            side,
+        })
+    }
+}
+
+/// Helper function for `Rect::sweep` which, for a single axis, returns the times the moving span
+/// (`min..max`) starts and stops overlapping the target span. `None` means they never overlap.
+fn sweep_axis(
+    min: f32,
+    max: f32,
+    target_min: f32,
+    target_max: f32,
+    delta: f32,
+) -> Option<(f32, f32)> {
+    if delta > 0. {
+        Some(((target_min - max) / delta, (target_max - min) / delta))
+    } else if delta < 0. {
+        Some(((target_max - min) / delta, (target_min - max) / delta))
+    } else if max > target_min && min < target_max {
+        Some((f32::NEG_INFINITY, f32::INFINITY))
+    } else {
+        None
+    }
+}
+
+impl Ray {
+    /// Create a new ray. `dir` doesn't need to be normalized, but it shouldn't be the zero vector.
+    pub fn new(origin: P2, dir: V2) -> Ray {
+        Ray {
+            origin,
+            dir: dir.normalized(),
+        }
+    }
+
+    /// Returns the point `distance` units along this ray.
+    pub fn at(&self, distance: f32) -> P2 {
+        self.origin + self.dir * distance
+    }
+
+    /// Returns where this ray first enters `rect`, or `None` if it misses it entirely.
+    ///
+    /// If the ray starts inside the rect, it is considered to intersect it immediately, with a
+    /// distance of 0 and a zero normal (since it didn't pass through any of the rect's sides).
+    pub fn intersect_rect(&self, rect: &Rect) -> Option<RayIntersection> {
+        let (entry_x, exit_x) = ray_axis(self.origin.x, self.dir.x, rect.left(), rect.right())?;
+        let (entry_y, exit_y) = ray_axis(self.origin.y, self.dir.y, rect.top(), rect.bottom())?;
+
+        let entry = entry_x.max(entry_y);
+        let exit = exit_x.min(exit_y);
+        if entry > exit || exit <= 0. {
+            return None;
+        }
+
+        if entry < 0. {
+            return Some(RayIntersection {
+                distance: 0.,
+                point: self.origin,
+                normal: V2::zero(),
+            });
+        }
+
+        let normal = if entry_x > entry_y {
+            V2::new(-self.dir.x.signum(), 0.)
+        } else {
+            V2::new(0., -self.dir.y.signum())
+        };
+        Some(RayIntersection {
+            distance: entry,
+            point: self.at(entry),
+            normal,
+        })
+    }
+
+    /// Returns where this ray first enters a circle at `center` with `radius`, or `None` if it
+    /// misses it entirely. As with `intersect_rect`, a ray starting inside the circle intersects
+    /// it immediately.
+    pub fn intersect_circle(&self, center: P2, radius: f32) -> Option<RayIntersection> {
+        let to_origin = self.origin - center;
+        let dist2 = to_origin.len2();
+        let radius2 = radius * radius;
+        if dist2 < radius2 {
+            return Some(RayIntersection {
+                distance: 0.,
+                point: self.origin,
+                normal: V2::zero(),
+            });
+        }
+
+        // Solve |origin + dir * t - center| = radius for t (dir is normalized, so a == 1)
+        let b = to_origin.x * self.dir.x + to_origin.y * self.dir.y;
+        let discriminant = b * b - (dist2 - radius2);
+        if discriminant < 0. {
+            return None;
+        }
+        let distance = -b - discriminant.sqrt();
+        if distance < 0. {
+            return None;
+        }
+
+        let point = self.at(distance);
+        Some(RayIntersection {
+            distance,
+            point,
+            normal: (point - center).normalized(),
+        })
+    }
+
+    /// Returns where this ray first enters `shape`, when fit inside `rect`, or `None` if it misses
+    /// it entirely. See also: `intersect_rect`.
+    pub fn intersect_shape(&self, shape: Shape, rect: &Rect) -> Option<RayIntersection> {
+        match shape {
+            Shape::Rect => self.intersect_rect(rect),
+            Shape::Circle => {
+                let (core, radius) = shape.core(rect);
+                self.intersect_circle(core.pos, radius)
+            }
+            Shape::Capsule => {
+                // A capsule is a rect capped on both ends by a circle
+                let (core, radius) = shape.core(rect);
+                let (rect_middle, end) = if core.size.x > 0. {
+                    let rect_middle = Rect::new(
+                        core.pos - V2::new(0., radius),
+                        V2::new(core.size.x, radius * 2.),
+                    );
+                    (rect_middle, P2::new(core.right(), core.top()))
+                } else {
+                    let rect_middle = Rect::new(
+                        core.pos - V2::new(radius, 0.),
+                        V2::new(radius * 2., core.size.y),
+                    );
+                    (rect_middle, P2::new(core.left(), core.bottom()))
+                };
+
+                let intersections = [
+                    self.intersect_rect(&rect_middle),
+                    self.intersect_circle(core.pos, radius),
+                    self.intersect_circle(end, radius),
+                ];
+                intersections
+                    .iter()
+                    .filter_map(|intersection| *intersection)
+                    .fold(
+                        None,
+                        |closest: Option<RayIntersection>, intersection| match closest {
+                            Some(closest) if closest.distance <= intersection.distance => {
+                                Some(closest)
+                            }
+                            _ => Some(intersection),
+                        },
+                    )
+            }
+        }
+    }
+}
+
+/// Helper function for `Ray::intersect_rect` which, for a single axis, returns the distances along
+/// the ray where it enters and leaves the span `min..max`. `None` means it never does.
+fn ray_axis(origin: f32, dir: f32, min: f32, max: f32) -> Option<(f32, f32)> {
+    if dir != 0. {
+        let t1 = (min - origin) / dir;
+        let t2 = (max - origin) / dir;
+        Some((t1.min(t2), t1.max(t2)))
+    } else if origin >= min && origin < max {
+        // Like `Rect::contains`, include the min edge but not the max edge, so that a ray running
+        // exactly along the seam between two adjacent rects can't slip between them
+        Some((f32::NEG_INFINITY, f32::INFINITY))
+    } else {
+        None
+    }
+}
+
+impl Shape {
+    /// Break this shape down into a rect "core" and a radius around it. This lets us treat every
+    /// shape the same way: a point is within the shape if it is within `radius` of the core.
+    ///
+    /// A rect is its own core with no radius, a circle's core is the single point at its center,
+    /// and a capsule's core is the line segment running down its middle.
+    fn core(self, rect: &Rect) -> (Rect, f32) {
+        match self {
+            Shape::Rect => (*rect, 0.),
+            Shape::Circle => {
+                let radius = rect.size.x.min(rect.size.y) / 2.;
+                (Rect::new(rect.pos + rect.size / 2., V2::zero()), radius)
+            }
+            Shape::Capsule => {
+                let radius = rect.size.x.min(rect.size.y) / 2.;
+                let inset = V2::new(radius, radius);
+                (Rect::new(rect.pos + inset, rect.size - inset * 2.), radius)
+            }
+        }
+    }
+
+    /// Returns `true` if `pt` is inside this shape, when fit inside `rect`.
+    pub fn contains(self, rect: &Rect, pt: P2) -> bool {
+        match self {
+            Shape::Rect => rect.contains(pt),
+            _ => {
+                let (core, radius) = self.core(rect);
+                separation(&Rect::new(pt, V2::zero()), &core).len() < radius
+            }
+        }
+    }
+
+    /// Returns `true` if this shape, when fit inside `rect`, overlaps the `other` shape, when fit
+    /// inside `other_rect`. Shapes which are only touching each other don't count.
+    pub fn overlaps(self, rect: &Rect, other: Shape, other_rect: &Rect) -> bool {
+        let (core, radius) = self.core(rect);
+        let (other_core, other_radius) = other.core(other_rect);
+        let radius = radius + other_radius;
+        if radius > 0. {
+            separation(&core, &other_core).len() < radius
+        } else {
+            core.overlaps(&other_core)
+        }
+    }
+
+    /// Like `overlaps`, but shapes which are only touching each other also count.
+    pub fn touches(self, rect: &Rect, other: Shape, other_rect: &Rect) -> bool {
+        let (core, radius) = self.core(rect);
+        let (other_core, other_radius) = other.core(other_rect);
+        let radius = radius + other_radius;
+        if radius > 0. {
+            // Round shapes are pushed apart with a little floating point error, so be generous
+            separation(&core, &other_core).len() <= radius + SWEEP_EPSILON
+        } else {
+            core.touches(&other_core)
+        }
+    }
+
+    /// If this shape overlaps the `other` shape (see `overlaps`), returns the shortest vector
+    /// that this shape would have to move by to not overlap it anymore.
+    pub fn penetration(self, rect: &Rect, other: Shape, other_rect: &Rect) -> Option<V2> {
+        if !self.overlaps(rect, other, other_rect) {
+            return None;
+        }
+
+        let (core, radius) = self.core(rect);
+        let (other_core, other_radius) = other.core(other_rect);
+        let radius = radius + other_radius;
+
+        let sep = separation(&core, &other_core);
+        let dist = sep.len();
+        if dist > 0. {
+            return Some(sep / dist * (radius - dist));
+        }
+
+        // Cores are overlapping, so push out the shortest way possible
+        let escapes = [
+            (core.right() - other_core.left(), V2::new(-1., 0.)),
+            (other_core.right() - core.left(), V2::new(1., 0.)),
+            (core.bottom() - other_core.top(), V2::new(0., -1.)),
+            (other_core.bottom() - core.top(), V2::new(0., 1.)),
+        ];
+        let (escape_dist, escape_dir) = escapes.iter().fold(escapes[0], |shortest, escape| {
+            if escape.0 < shortest.0 {
+                *escape
+            } else {
+                shortest
+            }
+        });
+        Some(escape_dir * (escape_dist + radius))
+    }
+}
+
+/// Returns the shortest vector from any point in `other` to any point in `rect`, or the zero vector
+/// if they touch or overlap.
+fn separation(rect: &Rect, other: &Rect) -> V2 {
+    let x = if rect.left() > other.right() {
+        rect.left() - other.right()
+    } else if rect.right() < other.left() {
+        rect.right() - other.left()
+    } else {
+        0.
+    };
+    let y = if rect.top() > other.bottom() {
+        rect.top() - other.bottom()
+    } else if rect.bottom() < other.top() {
+        rect.bottom() - other.top()
+    } else {
+        0.
+    };
+    V2::new(x, y)
 }
@@ -1,7 +1,11 @@
 use game2d::{
     self,
+    collide::layers::{Layer, LayerMask, LayerRegistry},
+    collide::replay::Replay,
+    collide::tiles::{TileFlags, TileLayer},
     collide::*,
     geom::{P2, V2},
+    shape::{Ray, Rect, RectSide, RectSides, Shape},
 };
 
 use std::time::Duration;
@@ -9,14 +13,41 @@ use std::time::Duration;
 mod test_support;
 use crate::test_support::*;
 
-const GROUP_WALL: u32 = GROUP_0;
-const GROUP_ACTOR: u32 = GROUP_1;
-const GROUP_PASSTHRU: u32 = GROUP_2;
+const LAYER_WALL: Layer = Layer::new(0);
+const LAYER_ACTOR: Layer = Layer::new(1);
+const LAYER_PASSTHRU: Layer = Layer::new(2);
+const LAYER_ENEMY: Layer = Layer::new(3);
+const LAYER_BLOCK: Layer = Layer::new(4);
+
+fn test_layers() -> LayerRegistry {
+    let mut layers = LayerRegistry::new();
+    layers.register(LAYER_WALL, "wall");
+    layers.register(LAYER_ACTOR, "actor");
+    layers.register(LAYER_PASSTHRU, "passthru");
+    layers.register(LAYER_ENEMY, "enemy");
+    layers.register(LAYER_BLOCK, "block");
+    layers
+}
 
 fn new_default_world() -> CollisionWorld {
     CollisionWorld::new(CollisionWorldParams {
-        group_pairs: vec![[GROUP_WALL, GROUP_ACTOR]],
-        partition_size: [20., 20.],
+        layer_pairs: vec![[LAYER_WALL, LAYER_ACTOR]],
+        ..CollisionWorldParams::new(test_layers())
+    })
+}
+
+/// A world where actors and enemies bump into walls and each other, with enemies giving way to
+/// actors
+fn new_crowded_world() -> CollisionWorld {
+    CollisionWorld::new(CollisionWorldParams {
+        layer_pairs: vec![
+            [LAYER_WALL, LAYER_ACTOR],
+            [LAYER_WALL, LAYER_ENEMY],
+            [LAYER_ACTOR, LAYER_ACTOR],
+            [LAYER_ACTOR, LAYER_ENEMY],
+        ],
+        yield_pairs: vec![[LAYER_ENEMY, LAYER_ACTOR]],
+        ..CollisionWorldParams::new(test_layers())
     })
 }
 
@@ -25,7 +56,7 @@ fn new_default_world() -> CollisionWorld {
 fn partition_x_must_be_positive() {
     CollisionWorld::new(CollisionWorldParams {
         partition_size: [0., 20.],
-        group_pairs: Vec::new(),
+        ..CollisionWorldParams::new(test_layers())
     });
 }
 
@@ -34,17 +65,26 @@ fn partition_x_must_be_positive() {
 fn partition_y_must_be_positive() {
     CollisionWorld::new(CollisionWorldParams {
         partition_size: [20., -20.],
-        group_pairs: Vec::new(),
+        ..CollisionWorldParams::new(test_layers())
+    });
+}
+
+#[test]
+#[should_panic(expected = "Invalid time step: 0ns")]
+fn time_step_must_be_positive() {
+    CollisionWorld::new(CollisionWorldParams {
+        time_step: Duration::from_millis(0),
+        ..CollisionWorldParams::new(test_layers())
     });
 }
 
 #[test]
 fn can_create_world_with_bodies() {
     let mut world = new_default_world();
-    world.new_body(GROUP_0, P2::new(0., 0.), V2::new(16., 16.));
-    world.new_body(GROUP_0, P2::new(0., 50.), V2::new(16., 16.));
+    world.new_body(LAYER_WALL, P2::new(0., 0.), V2::new(16., 16.));
+    world.new_body(LAYER_WALL, P2::new(0., 50.), V2::new(16., 16.));
     world.new_moving_body(
-        GROUP_0,
+        LAYER_WALL,
         P2::new(32., 32.),
         V2::new(16., 16.),
         V2::new(0., 0.),
@@ -52,26 +92,33 @@ fn can_create_world_with_bodies() {
 
     assert_eq!(world.bodies().count(), 3);
     assert_eq!(world.bodies_mut().count(), 3);
+    assert_eq!(world.bodies_with_handles().count(), 3);
 }
 
 #[test]
 fn can_remove_bodies() {
     let mut world = new_default_world();
-    let body1 = world.new_body(GROUP_0, P2::new(0., 0.), V2::new(16., 16.));
-    let body2 = world.new_body(GROUP_0, P2::new(0., 50.), V2::new(16., 16.));
+    let body1 = world.new_body(LAYER_WALL, P2::new(0., 0.), V2::new(16., 16.));
+    let body2 = world.new_body(LAYER_WALL, P2::new(0., 50.), V2::new(16., 16.));
     assert_eq!(world.bodies().count(), 2);
 
     world.remove_body(body1);
     assert_eq!(world.bodies().count(), 1);
+    assert_eq!(world.bodies_with_handles().next().unwrap().0, body2);
     world.remove_body(body2);
     assert_eq!(world.bodies().count(), 0);
+
+    world.shrink_to_fit();
+    let body3 = world.new_body(LAYER_WALL, P2::new(0., 0.), V2::new(16., 16.));
+    assert_eq!(world.body(body1).is_none(), true);
+    assert_eq!(world.body(body3).is_some(), true);
 }
 
 #[test]
 fn can_query_body_with_handle() {
     let mut world = new_default_world();
-    let body1 = world.new_body(GROUP_0, P2::new(0., 0.), V2::new(16., 16.));
-    let body2 = world.new_body(GROUP_0, P2::new(0., 50.), V2::new(16., 16.));
+    let body1 = world.new_body(LAYER_WALL, P2::new(0., 0.), V2::new(16., 16.));
+    let body2 = world.new_body(LAYER_WALL, P2::new(0., 50.), V2::new(16., 16.));
 
     assert_eq!(world.body(body1).unwrap().pos, P2::new(0., 0.));
     assert_eq!(world.body_mut(body1).unwrap().pos, P2::new(0., 0.));
@@ -86,8 +133,8 @@ fn can_query_body_with_handle() {
 #[test]
 fn can_mutate_bodies() {
     let mut world = new_default_world();
-    let body1 = world.new_body(GROUP_0, P2::new(0., 0.), V2::new(16., 16.));
-    let body2 = world.new_body(GROUP_0, P2::new(0., 50.), V2::new(16., 16.));
+    let body1 = world.new_body(LAYER_WALL, P2::new(0., 0.), V2::new(16., 16.));
+    let body2 = world.new_body(LAYER_WALL, P2::new(0., 50.), V2::new(16., 16.));
 
     world.body_mut(body1).unwrap().pos.x = 100.;
     world.body_mut(body2).unwrap().pos.x = 100.;
@@ -103,6 +150,150 @@ fn can_mutate_bodies() {
     assert_eq!(world.body(body2).unwrap().size, V2::new(10., 20.));
 }
 
+#[test]
+fn can_set_body_pos_and_vel() {
+    let mut world = new_default_world();
+    let actor = world.new_body(LAYER_ACTOR, P2::new(0., 0.), V2::new(20., 20.));
+
+    // Queries see the new position right away
+    world.set_pos(actor, P2::new(100., 0.));
+    assert_eq!(world.query_point(P2::new(110., 10.)), vec![actor]);
+    assert_eq!(world.query_point(P2::new(10., 10.)).len(), 0);
+    assert_eq!(world.body(actor).unwrap().prev_pos, P2::new(0., 0.));
+
+    world.set_vel(actor, V2::new(60., 0.));
+    world.elapse_time(Duration::from_millis(500));
+    assert_eq_f32(world.body(actor).unwrap().pos.x, 130., 0.1);
+
+    world.set_vel(actor, V2::zero());
+    world.elapse_time(Duration::from_millis(500));
+    assert_eq_f32(world.body(actor).unwrap().pos.x, 130., 0.1);
+}
+
+#[test]
+fn teleported_bodies_arent_interpolated() {
+    let mut world = new_default_world();
+    let actor = world.new_body(LAYER_ACTOR, P2::new(0., 0.), V2::new(20., 20.));
+
+    world.teleport(actor, P2::new(100., 50.));
+    let body = world.body(actor).unwrap();
+    assert_eq!(body.pos, P2::new(100., 50.));
+    assert_eq!(body.interpolated_pos(0.), P2::new(100., 50.));
+}
+
+#[test]
+fn bodies_cant_teleport_into_walls() {
+    let mut world = new_default_world();
+    world.set_tiles(new_tile_room());
+    let actor = world.new_body(LAYER_ACTOR, P2::new(40., 40.), V2::new(10., 10.));
+    world.new_body(LAYER_WALL, P2::new(100., 100.), V2::new(20., 20.));
+    world.new_body(LAYER_PASSTHRU, P2::new(60., 60.), V2::new(20., 20.));
+
+    assert_eq!(world.teleport(actor, P2::new(105., 105.)), false);
+    assert_eq!(world.teleport(actor, P2::new(5., 40.)), false); // Into a tile
+    assert_eq!(world.body(actor).unwrap().pos, P2::new(40., 40.));
+
+    // Flush against a wall is fine, and so is overlapping something we don't collide with
+    assert_eq!(world.teleport(actor, P2::new(90., 100.)), true);
+    assert_eq!(world.body(actor).unwrap().pos, P2::new(90., 100.));
+    assert_eq!(world.teleport(actor, P2::new(65., 65.)), true);
+    assert_eq!(world.body(actor).unwrap().pos, P2::new(65., 65.));
+}
+
+#[test]
+fn bodies_can_teleport_to_the_nearest_free_spot() {
+    let mut world = new_default_world();
+    let actor = world.new_body(LAYER_ACTOR, P2::new(0., 0.), V2::new(10., 10.));
+    world.new_body(LAYER_WALL, P2::new(50., 0.), V2::new(20., 20.));
+    world.new_body(LAYER_WALL, P2::new(30., 0.), V2::new(20., 20.));
+
+    // Nowhere free that close
+    assert_eq!(world.teleport_near(actor, P2::new(52., 4.), 5.), None);
+    assert_eq!(world.body(actor).unwrap().pos, P2::new(0., 0.));
+
+    // Sliding left out of one wall runs into the next, so going up is closer
+    assert_eq!(
+        world.teleport_near(actor, P2::new(52., 4.), 50.),
+        Some(P2::new(52., -10.))
+    );
+    assert_eq!(world.body(actor).unwrap().pos, P2::new(52., -10.));
+}
+
+#[test]
+fn bodies_can_move_and_slide_immediately() {
+    let mut world = new_default_world();
+    let actor = world.new_body(LAYER_ACTOR, P2::new(0., 0.), V2::new(20., 20.));
+    let wall = world.new_body(LAYER_WALL, P2::new(30., 0.), V2::new(20., 20.));
+
+    let result = world.move_and_slide(actor, V2::new(20., 5.)).unwrap();
+    assert_eq!(result.pos, P2::new(10., 5.));
+    assert_eq!(
+        result.contacts,
+        vec![MoveContact {
+            body: Some(wall),
+            side: RectSide::Left,
+            normal: V2::new(-1., 0.),
+        }]
+    );
+    assert_eq!(world.body(actor).unwrap().pos, P2::new(10., 5.));
+    assert_eq!(world.query_point(P2::new(15., 20.)), vec![actor]);
+
+    world.remove_body(actor);
+    assert_eq!(world.move_and_slide(actor, V2::new(20., 5.)), None);
+}
+
+#[test]
+fn mutated_bodies_keep_moving() {
+    let mut world = new_default_world();
+    let actor = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(0., 0.),
+        V2::new(20., 20.),
+        V2::new(60., 0.),
+    );
+    let other = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(0., 50.),
+        V2::new(20., 20.),
+        V2::new(60., 0.),
+    );
+
+    world.elapse_time(Duration::from_millis(250));
+    world.body_mut(actor).unwrap().size = V2::new(10., 10.);
+    for _ in world.bodies_mut() {}
+    world.elapse_time(Duration::from_millis(250));
+    assert_eq!(
+        world.body(actor).unwrap().pos.x,
+        world.body(other).unwrap().pos.x
+    );
+}
+
+#[test]
+fn removed_bodies_are_ignored() {
+    let mut world = new_default_world();
+    let actor = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(0., 0.),
+        V2::new(20., 20.),
+        V2::new(60., 0.),
+    );
+    world.body_mut(actor).unwrap().vel = V2::new(0., 60.);
+    world.remove_body(actor);
+
+    // None of these do anything, even if a new body takes the removed one's place
+    let wall = world.new_body(LAYER_WALL, P2::new(50., 50.), V2::new(20., 20.));
+    world.remove_body(actor);
+    world.set_pos(actor, P2::new(100., 100.));
+    world.teleport(actor, P2::new(100., 100.));
+    world.set_vel(actor, V2::new(60., 0.));
+    world.apply_impulse(actor, V2::new(60., 0.));
+    assert_eq!(world.body_mut(actor).is_none(), true);
+
+    world.elapse_time(Duration::from_millis(500));
+    assert_eq!(world.bodies().count(), 1);
+    assert_eq!(world.body(wall).unwrap().pos, P2::new(50., 50.));
+}
+
 /// +-------+     +-------+           +-------+-------+
 /// |       |     |       |           |       |       |
 /// |       | ←←← |       |  ======>  |       |       |
@@ -112,9 +303,9 @@ fn can_mutate_bodies() {
 fn collide_dynamic_with_single_static_body_moving_left() {
     let mut world = new_default_world();
 
-    let wall = world.new_body(GROUP_WALL, P2::new(0., 0.), V2::new(20., 20.));
+    let wall = world.new_body(LAYER_WALL, P2::new(0., 0.), V2::new(20., 20.));
     let actor = world.new_moving_body(
-        GROUP_ACTOR,
+        LAYER_ACTOR,
         P2::new(30., 0.),
         V2::new(20., 20.),
         V2::new(-1., 0.),
@@ -144,9 +335,9 @@ fn collide_dynamic_with_single_static_body_moving_left() {
 fn collide_dynamic_with_single_static_body_moving_right() {
     let mut world = new_default_world();
 
-    world.new_body(GROUP_WALL, P2::new(30., 0.), V2::new(20., 20.));
+    world.new_body(LAYER_WALL, P2::new(30., 0.), V2::new(20., 20.));
     let actor = world.new_moving_body(
-        GROUP_ACTOR,
+        LAYER_ACTOR,
         P2::new(0., 0.),
         V2::new(20., 20.),
         V2::new(1., 0.),
@@ -175,9 +366,9 @@ fn collide_dynamic_with_single_static_body_moving_right() {
 fn collide_dynamic_with_single_static_body_moving_up() {
     let mut world = new_default_world();
 
-    world.new_body(GROUP_WALL, P2::new(0., 0.), V2::new(20., 20.));
+    world.new_body(LAYER_WALL, P2::new(0., 0.), V2::new(20., 20.));
     let actor = world.new_moving_body(
-        GROUP_ACTOR,
+        LAYER_ACTOR,
         P2::new(0., 30.),
         V2::new(20., 20.),
         V2::new(0., -1.),
@@ -206,9 +397,9 @@ fn collide_dynamic_with_single_static_body_moving_up() {
 fn collide_dynamic_with_single_static_body_moving_down() {
     let mut world = new_default_world();
 
-    world.new_body(GROUP_WALL, P2::new(0., 30.), V2::new(20., 20.));
+    world.new_body(LAYER_WALL, P2::new(0., 30.), V2::new(20., 20.));
     let actor = world.new_moving_body(
-        GROUP_ACTOR,
+        LAYER_ACTOR,
         P2::new(0., 0.),
         V2::new(20., 20.),
         V2::new(0., 1.),
@@ -236,10 +427,10 @@ fn collide_dynamic_with_single_static_body_moving_down() {
 fn collide_dynamic_with_two_static_bodies() {
     let mut world = new_default_world();
 
-    world.new_body(GROUP_WALL, P2::new(0., 0.), V2::new(20., 20.));
-    world.new_body(GROUP_WALL, P2::new(0., 20.), V2::new(20., 20.));
+    world.new_body(LAYER_WALL, P2::new(0., 0.), V2::new(20., 20.));
+    world.new_body(LAYER_WALL, P2::new(0., 20.), V2::new(20., 20.));
     let actor = world.new_moving_body(
-        GROUP_ACTOR,
+        LAYER_ACTOR,
         P2::new(30., 10.),
         V2::new(20., 20.),
         V2::new(-1., 0.),
@@ -269,10 +460,10 @@ fn collide_dynamic_with_two_static_bodies() {
 fn collide_dynamic_into_corner() {
     let mut world = new_default_world();
 
-    world.new_body(GROUP_WALL, P2::new(20., 0.), V2::new(20., 20.));
-    world.new_body(GROUP_WALL, P2::new(0., 20.), V2::new(20., 20.));
+    world.new_body(LAYER_WALL, P2::new(20., 0.), V2::new(20., 20.));
+    world.new_body(LAYER_WALL, P2::new(0., 20.), V2::new(20., 20.));
     let actor = world.new_moving_body(
-        GROUP_ACTOR,
+        LAYER_ACTOR,
         P2::new(40., 40.),
         V2::new(20., 20.),
         V2::new(-1., -1.),
@@ -302,10 +493,10 @@ fn collide_dynamic_into_corner() {
 fn dynamic_body_slides_across_static_bodies() {
     let mut world = new_default_world();
 
-    world.new_body(GROUP_WALL, P2::new(0., 0.), V2::new(20., 20.));
-    world.new_body(GROUP_WALL, P2::new(0., 20.), V2::new(20., 20.));
+    world.new_body(LAYER_WALL, P2::new(0., 0.), V2::new(20., 20.));
+    world.new_body(LAYER_WALL, P2::new(0., 20.), V2::new(20., 20.));
     let actor = world.new_moving_body(
-        GROUP_ACTOR,
+        LAYER_ACTOR,
         P2::new(20., 30.),
         V2::new(20., 20.),
         V2::new(-1., -1.),
@@ -317,6 +508,190 @@ fn dynamic_body_slides_across_static_bodies() {
     assert_eq_f32(world.body(actor).unwrap().pos.y, 10., 0.1);
 }
 
+/// +-------+      +-+           +-------+-+
+/// |       |      | |           |       | |
+/// |       | →→→→ | |  ======>  |       | |
+/// |       |      | |           |       | |
+/// +-------+      +-+           +-------+-+
+///
+/// A body moving far enough in a single step to jump clear over a thin wall should still hit it.
+#[test]
+fn fast_dynamic_body_does_not_tunnel_through_thin_wall() {
+    let mut world = new_default_world();
+
+    world.new_body(LAYER_WALL, P2::new(50., -20.), V2::new(2., 60.));
+    let actor = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(0., 0.),
+        V2::new(20., 20.),
+        V2::new(6000., 0.), // 100px per 60fps step
+    );
+
+    world.elapse_time(Duration::from_millis(100));
+
+    assert_eq_f32(world.body(actor).unwrap().pos.x, 30., 0.1);
+}
+
+/// +-------+                 +-------+            +-------+-------+
+/// |       |                 |       |            |       |       |
+/// |   A   | →→→→       ←←←← |   B   |  ======>   |   A   |   B   |
+/// |       |                 |       |            |       |       |
+/// +-------+                 +-------+            +-------+-------+
+#[test]
+fn moving_bodies_collide_head_on() {
+    let mut world = new_crowded_world();
+
+    let actor_a = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(0., 0.),
+        V2::new(20., 20.),
+        V2::new(60., 0.),
+    );
+    let actor_b = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(100., 0.),
+        V2::new(20., 20.),
+        V2::new(-60., 0.),
+    );
+
+    world.elapse_time(Duration::from_secs(2));
+
+    let pos_a = world.body(actor_a).unwrap().pos;
+    let pos_b = world.body(actor_b).unwrap().pos;
+    // They meet roughly in the middle, although whoever goes first on the last step gets a bit
+    // further
+    assert_eq_f32(pos_a.x, 40., 1.5);
+    assert_eq_f32(pos_b.x - pos_a.x, 20., 0.01);
+}
+
+///              +-------+                                                      +-------+
+///              |       |                                                      |       |
+///              |   B   |                   +-------+                          |   B   |
+///              |       |                   |       |                          |       |
+/// +-------+    +-------+          +--------|   B   |                          +-------+
+/// |       |        ↑     ======>  |       ||       |  ======>          +-------+
+/// |   A   | →→→→   ↑              |   A   |+-------+                   |       |
+/// |       |        ↑              |       |                            |   A   |
+/// +-------+                       +-------+                            |       |
+///                                                                      +-------+
+///
+/// (Diagram is upside down relative to the test, with y increasing upwards.) B can't move while
+/// A is crossing its path, but afterwards, it continues on its way.
+#[test]
+fn moving_bodies_collide_perpendicular() {
+    let mut world = new_crowded_world();
+
+    let actor_a = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(0., 0.),
+        V2::new(20., 20.),
+        V2::new(60., 0.),
+    );
+    let actor_b = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(25., 30.),
+        V2::new(20., 20.),
+        V2::new(0., -60.),
+    );
+
+    world.elapse_time(Duration::from_millis(334)); // A moves 20px
+
+    // B walked into the bottom of A and got stuck
+    assert_eq_f32(world.body(actor_a).unwrap().pos.x, 20., 0.1);
+    assert_eq_f32(world.body(actor_a).unwrap().pos.y, 0., 0.1);
+    assert_eq_f32(world.body(actor_b).unwrap().pos.x, 25., 0.1);
+    assert_eq_f32(world.body(actor_b).unwrap().pos.y, 20., 0.1);
+
+    world.elapse_time(Duration::from_millis(1333)); // A moves 80px more
+
+    // A got out of the way, so B was able to continue on
+    assert_eq_f32(world.body(actor_a).unwrap().pos.x, 100., 0.1);
+    assert_eq_f32(world.body(actor_b).unwrap().pos.y, -35., 1.5);
+}
+
+/// +-------+    +-------+                  +-------+-------+
+/// |       |    |       |                  |       |       |
+/// |   A   | →→ |   B   | →       ======>  |   A   |   B   | →
+/// |       |    |       |                  |       |       |
+/// +-------+    +-------+                  +-------+-------+
+///
+/// A fast body chasing a slow one should stay right on its tail, instead of bumping into where it
+/// used to be.
+#[test]
+fn moving_bodies_collide_when_chasing() {
+    let mut world = new_crowded_world();
+
+    // Create the slower body first, so it doesn't get a head start by being created first
+    let actor_b = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(30., 0.),
+        V2::new(20., 20.),
+        V2::new(60., 0.),
+    );
+    let actor_a = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(0., 0.),
+        V2::new(20., 20.),
+        V2::new(120., 0.),
+    );
+
+    world.elapse_time(Duration::from_secs(1));
+
+    let pos_a = world.body(actor_a).unwrap().pos;
+    let pos_b = world.body(actor_b).unwrap().pos;
+    assert_eq_f32(pos_b.x, 90., 0.1);
+    assert_eq_f32(pos_b.x - pos_a.x, 20., 0.001);
+}
+
+/// +-------+    +-------+         +-------+           +-------+-------+-------+
+/// |       |    |       |         |       |           |       |       |       |
+/// | Actor | →→ | Enemy |         | Wall  |  ======>  | Actor | Enemy | Wall  |
+/// |       |    |       |         |       |           |       |       |       |
+/// +-------+    +-------+         +-------+           +-------+-------+-------+
+#[test]
+fn moving_bodies_push_bodies_that_yield_to_them() {
+    let mut world = new_crowded_world();
+
+    world.new_body(LAYER_WALL, P2::new(100., 0.), V2::new(20., 20.));
+    let enemy = world.new_body(LAYER_ENEMY, P2::new(40., 0.), V2::new(20., 20.));
+    let actor = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(0., 0.),
+        V2::new(20., 20.),
+        V2::new(60., 0.),
+    );
+
+    world.elapse_time(Duration::from_millis(500));
+
+    // Enemy is getting pushed along
+    assert_eq_f32(world.body(actor).unwrap().pos.x, 30., 0.1);
+    assert_eq_f32(world.body(enemy).unwrap().pos.x, 50., 0.1);
+
+    world.elapse_time(Duration::from_secs(2));
+
+    // Until it can't be pushed anymore
+    assert_eq_f32(world.body(actor).unwrap().pos.x, 60., 0.1);
+    assert_eq_f32(world.body(enemy).unwrap().pos.x, 80., 0.1);
+}
+
+#[test]
+fn moving_bodies_dont_push_bodies_that_dont_yield_to_them() {
+    let mut world = new_crowded_world();
+
+    let actor = world.new_body(LAYER_ACTOR, P2::new(0., 0.), V2::new(20., 20.));
+    let enemy = world.new_moving_body(
+        LAYER_ENEMY,
+        P2::new(40., 0.),
+        V2::new(20., 20.),
+        V2::new(-60., 0.),
+    );
+
+    world.elapse_time(Duration::from_secs(2));
+
+    assert_eq_f32(world.body(actor).unwrap().pos.x, 0., 0.1);
+    assert_eq_f32(world.body(enemy).unwrap().pos.x, 20., 0.1);
+}
+
 /// +-------+     +-------+           +-------+-------+               +-------+     +-------+
 /// |       |     |       |           |       |       |  move A       |       |     |       |
 /// |   A   | ←←← |   B   |  ======>  |   A   |   B   |  ======>  ←←← |   B   |     |   A   |
@@ -326,9 +701,9 @@ fn dynamic_body_slides_across_static_bodies() {
 fn can_mutate_body_to_move_it() {
     let mut world = new_default_world();
 
-    let wall = world.new_body(GROUP_WALL, P2::new(0., 0.), V2::new(20., 20.));
+    let wall = world.new_body(LAYER_WALL, P2::new(0., 0.), V2::new(20., 20.));
     let actor = world.new_moving_body(
-        GROUP_ACTOR,
+        LAYER_ACTOR,
         P2::new(30., 0.),
         V2::new(20., 20.),
         V2::new(-1., 0.),
@@ -353,33 +728,1716 @@ fn can_mutate_body_to_move_it() {
     assert_eq_f32(world.body(actor).unwrap().pos.x, 80., 0.1);
 }
 
-/// +-------+     +-------+           +-----+-------+
-/// |       |     |       |           |     |       |
-/// |       | ←←← |       |  ======>  | ←←← |       |
-/// |       |     |       |           |     |       |
-/// +-------+     +-------+           +-----+-------+
 #[test]
-fn bodies_only_collide_if_groups_are_registered_to_collide() {
-    let mut world = CollisionWorld::new(CollisionWorldParams {
-        group_pairs: vec![],
-        partition_size: [40., 40.],
-    });
+fn contacts_between_bodies_generate_events() {
+    let mut world = new_default_world();
 
-    let wall = world.new_body(GROUP_WALL, P2::new(0., 0.), V2::new(20., 20.));
+    let wall = world.new_body(LAYER_WALL, P2::new(0., 0.), V2::new(20., 20.));
     let actor = world.new_moving_body(
-        GROUP_ACTOR,
-        P2::new(30., 0.),
+        LAYER_ACTOR,
+        P2::new(29.5, 0.),
         V2::new(20., 20.),
-        V2::new(-1., 0.),
+        V2::new(-60., 0.),
     );
 
-    world.elapse_time(Duration::from_secs(20));
+    world.elapse_time(Duration::from_millis(150));
+    assert_eq!(world.drain_events().count(), 0);
+
+    // Actor walks into the wall
+    world.elapse_time(Duration::from_millis(20));
+    let events: Vec<ContactEvent> = world.drain_events().collect();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, ContactKind::Began);
+    assert_eq!(events[0].body, actor);
+    assert_eq!(events[0].other, wall);
+    assert_eq!(events[0].side, RectSide::Right);
+    assert_eq!(events[0].normal, V2::new(1., 0.));
+
+    // Draining clears the events
+    assert_eq!(world.drain_events().count(), 0);
+
+    // Actor keeps pushing against the wall, one event per step
+    world.elapse_time(Duration::from_millis(50));
+    let events: Vec<ContactEvent> = world.drain_events().collect();
+    assert_eq!(events.len(), 3);
+    assert_eq!(
+        events.iter().all(|e| e.kind == ContactKind::Persisted),
+        true
+    );
 
-    // Player passed through the wall
-    assert_eq_f32(world.body(actor).unwrap().pos.x, 10., 0.1);
+    // Actor stops moving, but is still touching the wall
+    world.body_mut(actor).unwrap().vel = V2::zero();
+    world.elapse_time(Duration::from_millis(20));
+    let events: Vec<ContactEvent> = world.drain_events().collect();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, ContactKind::Persisted);
+
+    // Actor walks away
+    world.body_mut(actor).unwrap().vel = V2::new(60., 0.);
+    world.elapse_time(Duration::from_millis(20));
+    let events: Vec<ContactEvent> = world.drain_events().collect();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, ContactKind::Ended);
+    assert_eq!(events[0].body, actor);
+    assert_eq!(events[0].other, wall);
+    assert_eq!(events[0].side, RectSide::Right);
+}
 
-    // The wall didn't budge
+#[test]
+fn removing_a_body_ends_its_contacts() {
+    let mut world = new_default_world();
+
+    let wall = world.new_body(LAYER_WALL, P2::new(0., 0.), V2::new(20., 20.));
+    world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(20., 0.),
+        V2::new(20., 20.),
+        V2::new(-60., 0.),
+    );
+
+    world.elapse_time(Duration::from_millis(20));
+    assert_eq!(
+        world.drain_events().next().unwrap().kind,
+        ContactKind::Began
+    );
+
+    world.remove_body(wall);
+    world.elapse_time(Duration::from_millis(20));
+    let events: Vec<ContactEvent> = world.drain_events().collect();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, ContactKind::Ended);
+    assert_eq!(events[0].other, wall);
+}
+
+#[test]
+fn contacts_between_two_moving_bodies_are_only_reported_once() {
+    let mut world = new_crowded_world();
+
+    world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(0., 0.),
+        V2::new(20., 20.),
+        V2::new(60., 0.),
+    );
+    world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(20., 0.),
+        V2::new(20., 20.),
+        V2::new(-60., 0.),
+    );
+
+    world.elapse_time(Duration::from_millis(20));
+    let events: Vec<ContactEvent> = world.drain_events().collect();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, ContactKind::Began);
+}
+
+#[test]
+fn bodies_that_cant_collide_dont_generate_events() {
+    let mut world = new_default_world();
+
+    world.new_body(LAYER_PASSTHRU, P2::new(0., 0.), V2::new(20., 20.));
+    world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(20., 0.),
+        V2::new(20., 20.),
+        V2::new(-60., 0.),
+    );
+
+    world.elapse_time(Duration::from_millis(500));
+    assert_eq!(world.drain_events().count(), 0);
+}
+
+/// +.......+     +-------+           +-------+   +.......+
+/// :       :     |       |           |       |   :       :
+/// :       : ←←← |       |  ======>  |       |   :       :
+/// :       :     |       |           |       |   :       :
+/// +.......+     +-------+           +-------+   +.......+
+///
+/// In the above diagram, the sensor is drawn with dotted lines.
+#[test]
+fn sensors_report_overlaps_without_blocking() {
+    let mut world = new_default_world();
+
+    let sensor = world.new_sensor(LAYER_WALL, P2::new(0., 0.), V2::new(20., 20.));
+    let actor = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(20., 0.),
+        V2::new(20., 20.),
+        V2::new(-60., 0.),
+    );
+
+    world.elapse_time(Duration::from_millis(20));
+    assert_eq_f32(world.body(actor).unwrap().pos.x, 19., 0.1);
+    assert_eq!(
+        world.drain_events().next().unwrap().kind,
+        ContactKind::Began
+    );
+
+    world.elapse_time(Duration::from_millis(20));
+    let events: Vec<ContactEvent> = world.drain_events().collect();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].kind, ContactKind::Persisted);
+    assert_eq!(events[0].body, actor);
+    assert_eq!(events[0].other, sensor);
+    assert_eq!(world.get_overlapping(actor).len(), 1);
+    assert_eq!(world.get_touching(actor).len(), 0);
+
+    // The actor passes right through the sensor
+    world.elapse_time(Duration::from_millis(800));
+    assert_eq_f32(world.body(actor).unwrap().pos.x, -30., 0.1);
+    let events: Vec<ContactEvent> = world.drain_events().collect();
+    assert_eq!(events.last().unwrap().kind, ContactKind::Ended);
+    assert_eq!(world.get_overlapping(actor).len(), 0);
+}
+
+#[test]
+fn moving_sensors_pass_through_everything() {
+    let mut world = new_default_world();
+
+    let wall = world.new_body(LAYER_WALL, P2::new(0., 0.), V2::new(20., 20.));
+    let sensor = world.new_sensor(LAYER_ACTOR, P2::new(40., 0.), V2::new(20., 20.));
+    world.body_mut(sensor).unwrap().vel = V2::new(-60., 0.);
+
+    world.elapse_time(Duration::from_millis(500));
+    assert_eq_f32(world.body(sensor).unwrap().pos.x, 10., 0.1);
     assert_eq_f32(world.body(wall).unwrap().pos.x, 0., 0.1);
+
+    let events: Vec<ContactEvent> = world.drain_events().collect();
+    assert_eq!(events.last().unwrap().kind, ContactKind::Persisted);
+    assert_eq!(events.last().unwrap().body, sensor);
+    assert_eq!(events.last().unwrap().other, wall);
+}
+
+#[test]
+fn can_query_touching_bodies() {
+    let mut world = new_default_world();
+
+    let actor = world.new_body(LAYER_ACTOR, P2::new(20., 20.), V2::new(20., 20.));
+    world.new_body(LAYER_WALL, P2::new(0., 20.), V2::new(20., 20.)); // Left
+    world.new_body(LAYER_WALL, P2::new(30., 0.), V2::new(20., 20.)); // Top
+    world.new_body(LAYER_PASSTHRU, P2::new(30., 30.), V2::new(20., 20.)); // Overlapping
+    world.new_body(LAYER_WALL, P2::new(41., 20.), V2::new(20., 20.)); // Not quite touching
+    world.new_sensor(LAYER_WALL, P2::new(20., 40.), V2::new(20., 20.)); // Sensor
+    world.new_body(LAYER_WALL, P2::new(200., 200.), V2::new(20., 20.)); // Far away
+
+    assert_eq!(world.get_touching(actor).len(), 3);
+    assert_eq!(world.get_overlapping(actor).len(), 1);
+}
+
+#[test]
+fn bodies_accelerate_up_to_their_max_speed() {
+    let mut world = new_default_world();
+    let actor = world.new_body(LAYER_ACTOR, P2::new(0., 0.), V2::new(20., 20.));
+    {
+        let body = world.body_mut(actor).unwrap();
+        body.accel = V2::new(100., 0.);
+        body.max_speed = Some(50.);
+    }
+
+    world.elapse_time(Duration::from_millis(250));
+    assert_eq_f32(world.body(actor).unwrap().vel.x, 25., 0.1);
+
+    world.elapse_time(Duration::from_secs(1));
+    assert_eq_f32(world.body(actor).unwrap().vel.x, 50., 0.001);
+
+    // Impulses can go past max speed, but acceleration can't take a body any further
+    world.apply_impulse(actor, V2::new(100., 0.));
+    world.elapse_time(Duration::from_millis(500));
+    assert_eq_f32(world.body(actor).unwrap().vel.x, 150., 0.001);
+}
+
+#[test]
+fn impulses_are_slowed_down_by_friction() {
+    let mut world = new_default_world();
+    let actor = world.new_body(LAYER_ACTOR, P2::new(0., 0.), V2::new(20., 20.));
+    world.body_mut(actor).unwrap().friction = 100.;
+
+    world.apply_impulse(actor, V2::new(100., 0.));
+    world.elapse_time(Duration::from_millis(500));
+    assert_eq_f32(world.body(actor).unwrap().vel.x, 50., 0.1);
+
+    // Friction stops the body rather than turning it around
+    world.elapse_time(Duration::from_secs(2));
+    assert_eq!(world.body(actor).unwrap().vel, V2::zero());
+    assert_eq_f32(world.body(actor).unwrap().pos.x, 50., 1.);
+}
+
+#[test]
+fn impulses_are_slowed_down_by_damping() {
+    let mut world = new_default_world();
+    let actor = world.new_body(LAYER_ACTOR, P2::new(0., 0.), V2::new(20., 20.));
+    world.body_mut(actor).unwrap().damping = 2f32.ln();
+
+    world.apply_impulse(actor, V2::new(0., -100.));
+    world.elapse_time(Duration::from_secs(1));
+    assert_eq_f32(world.body(actor).unwrap().vel.y, -50., 0.1);
+}
+
+#[test]
+fn impulses_are_blocked_by_walls() {
+    let mut world = new_default_world();
+    let actor = world.new_body(LAYER_ACTOR, P2::new(0., 0.), V2::new(20., 20.));
+    world.new_body(LAYER_WALL, P2::new(40., 0.), V2::new(20., 20.));
+    world.body_mut(actor).unwrap().damping = 2.;
+
+    world.apply_impulse(actor, V2::new(200., 0.));
+    world.elapse_time(Duration::from_secs(1));
+    assert_eq!(world.body(actor).unwrap().pos, P2::new(20., 0.));
+}
+
+#[test]
+fn queries_return_handles_and_user_data() {
+    let mut world = new_default_world();
+
+    let actor = world.new_body(LAYER_ACTOR, P2::new(20., 20.), V2::new(20., 20.));
+    let wall = world.new_body(LAYER_WALL, P2::new(0., 20.), V2::new(20., 20.));
+    let sensor = world.new_sensor(LAYER_WALL, P2::new(30., 30.), V2::new(20., 20.));
+    world.body_mut(wall).unwrap().user_data = 7;
+    world.body_mut(sensor).unwrap().user_data = 9;
+
+    let touching = world.get_touching(actor);
+    assert_eq!(touching.len(), 1);
+    assert_eq!(touching[0].0, wall);
+    assert_eq!(touching[0].1.user_data, 7);
+
+    let overlapping = world.get_overlapping(actor);
+    assert_eq!(overlapping.len(), 1);
+    assert_eq!(overlapping[0].0, sensor);
+    assert_eq!(overlapping[0].1.user_data, 9);
+}
+
+#[test]
+fn can_query_bodies_in_rect() {
+    let mut world = new_default_world();
+
+    let wall1 = world.new_body(LAYER_WALL, P2::new(0., 0.), V2::new(20., 20.));
+    let wall2 = world.new_body(LAYER_WALL, P2::new(100., 0.), V2::new(20., 20.));
+    let actor = world.new_body(LAYER_ACTOR, P2::new(50., 0.), V2::new(20., 20.));
+    world.new_body(LAYER_WALL, P2::new(500., 500.), V2::new(20., 20.));
+
+    let query = Rect::new(P2::new(10., 10.), V2::new(100., 100.));
+    let mut results = world.query_rect(query, LAYER_WALL | LAYER_ACTOR);
+    results.sort_by_key(|h| world.body(*h).unwrap().pos.x as i32);
+    assert_eq!(results, vec![wall1, actor, wall2]);
+
+    assert_eq!(world.query_rect(query, LAYER_ACTOR), vec![actor]);
+    assert_eq!(world.query_rect(query, LAYER_PASSTHRU).len(), 0);
+
+    // Flush isn't the same as overlapping
+    let query = Rect::new(P2::new(20., 0.), V2::new(30., 20.));
+    assert_eq!(world.query_rect(query, LAYER_WALL | LAYER_ACTOR).len(), 0);
+}
+
+#[test]
+fn can_query_bodies_at_point() {
+    let mut world = new_default_world();
+
+    let wall = world.new_body(LAYER_WALL, P2::new(0., 0.), V2::new(20., 20.));
+    let sensor = world.new_sensor(LAYER_WALL, P2::new(10., 10.), V2::new(20., 20.));
+
+    assert_eq!(world.query_point(P2::new(5., 5.)), vec![wall]);
+    assert_eq!(world.query_point(P2::new(25., 25.)), vec![sensor]);
+    assert_eq!(world.query_point(P2::new(15., 15.)).len(), 2);
+    assert_eq!(world.query_point(P2::new(50., 50.)).len(), 0);
+}
+
+#[test]
+fn can_raycast_through_bodies() {
+    let mut world = new_default_world();
+
+    let wall_near = world.new_body(LAYER_WALL, P2::new(40., 0.), V2::new(20., 20.));
+    let wall_far = world.new_body(LAYER_WALL, P2::new(100., 0.), V2::new(20., 20.));
+    world.new_body(LAYER_WALL, P2::new(70., 40.), V2::new(20., 20.)); // Not in the way
+    let wall_origin = world.new_body(LAYER_WALL, P2::new(0., 0.), V2::new(20., 20.));
+    world.new_sensor(LAYER_WALL, P2::new(25., 0.), V2::new(10., 20.)); // Rays ignore sensors
+    let actor = world.new_body(LAYER_ACTOR, P2::new(200., 0.), V2::new(20., 20.));
+
+    let hit = world
+        .raycast(
+            Ray::new(P2::new(30., 10.), V2::new(1., 0.)),
+            1000.,
+            LAYER_WALL,
+        )
+        .unwrap();
+    assert_eq!(hit.body, Some(wall_near));
+    assert_eq!(hit.point, P2::new(40., 10.));
+    assert_eq!(hit.normal, V2::new(-1., 0.));
+    assert_eq_f32(hit.distance, 10., 0.001);
+
+    // Rays starting inside a body hit it immediately
+    let hit = world
+        .raycast(
+            Ray::new(P2::new(10., 10.), V2::new(1., 0.)),
+            1000.,
+            LAYER_WALL,
+        )
+        .unwrap();
+    assert_eq!(hit.body, Some(wall_origin));
+    assert_eq!(hit.distance, 0.);
+    assert_eq!(hit.normal, V2::zero());
+
+    // Rays can stop short
+    assert_eq!(
+        world
+            .raycast(
+                Ray::new(P2::new(30., 10.), V2::new(-1., 0.)),
+                5.,
+                LAYER_WALL
+            )
+            .is_none(),
+        true
+    );
+
+    // Rays can be filtered by layer
+    let hit = world
+        .raycast(
+            Ray::new(P2::new(30., 10.), V2::new(1., 0.)),
+            1000.,
+            LAYER_ACTOR,
+        )
+        .unwrap();
+    assert_eq!(hit.body, Some(actor));
+
+    let hits: Vec<BodyHandle> = world
+        .raycast_all(
+            Ray::new(P2::new(10., 10.), V2::new(1., 0.)),
+            1000.,
+            LAYER_WALL | LAYER_ACTOR,
+        )
+        .into_iter()
+        .filter_map(|hit| hit.body)
+        .collect();
+    assert_eq!(hits, vec![wall_origin, wall_near, wall_far, actor]);
+}
+
+#[test]
+fn raycasts_walk_through_the_grid_in_any_direction() {
+    let mut world = new_default_world();
+
+    // A ring of walls around the origin, spread across many grid cells
+    let mut walls = Vec::new();
+    for i in -5..5 {
+        let i = i as f32 * 20.;
+        walls.push(world.new_body(LAYER_WALL, P2::new(i, -100.), V2::new(20., 20.)));
+        walls.push(world.new_body(LAYER_WALL, P2::new(i, 80.), V2::new(20., 20.)));
+        walls.push(world.new_body(LAYER_WALL, P2::new(-100., i), V2::new(20., 20.)));
+        walls.push(world.new_body(LAYER_WALL, P2::new(80., i), V2::new(20., 20.)));
+    }
+
+    let origin = P2::new(0., 3.);
+    for (dir, expected_point) in &[
+        (V2::new(1., 0.), P2::new(80., 3.)),
+        (V2::new(-1., 0.), P2::new(-80., 3.)),
+        (V2::new(0., 1.), P2::new(0., 80.)),
+        (V2::new(0., -1.), P2::new(0., -80.)),
+        (V2::new(1., 1.), P2::new(77., 80.)),
+        (V2::new(-2., 1.), P2::new(-80., 43.)),
+    ] {
+        let hit = world
+            .raycast(Ray::new(origin, *dir), 1000., LAYER_WALL)
+            .unwrap();
+        assert_eq_f32(hit.point.x, expected_point.x, 0.01);
+        assert_eq_f32(hit.point.y, expected_point.y, 0.01);
+    }
+}
+
+#[test]
+fn can_check_line_of_sight() {
+    let mut world = new_default_world();
+
+    world.new_body(LAYER_WALL, P2::new(40., 0.), V2::new(20., 20.));
+
+    assert_eq!(
+        world.line_of_sight(P2::new(0., 10.), P2::new(100., 10.), LAYER_WALL),
+        false
+    );
+    assert_eq!(
+        world.line_of_sight(P2::new(0., 10.), P2::new(30., 10.), LAYER_WALL),
+        true
+    );
+    assert_eq!(
+        world.line_of_sight(P2::new(0., 30.), P2::new(100., 30.), LAYER_WALL),
+        true
+    );
+    assert_eq!(
+        world.line_of_sight(P2::new(0., 10.), P2::new(100., 10.), LAYER_ACTOR),
+        true
+    );
+}
+
+#[test]
+fn round_bodies_slide_around_each_other() {
+    let mut world = new_default_world();
+
+    let wall = world.new_body(LAYER_WALL, P2::new(50., 0.), V2::new(20., 20.));
+    world.body_mut(wall).unwrap().shape = Shape::Circle;
+    let actor = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(0., 4.),
+        V2::new(20., 20.),
+        V2::new(100., 0.),
+    );
+    world.body_mut(actor).unwrap().shape = Shape::Circle;
+
+    for _ in 0..60 {
+        world.elapse_time(Duration::from_millis(17));
+        let actor_body = world.body(actor).unwrap();
+        let wall_body = world.body(wall).unwrap();
+        assert_eq!(
+            actor_body.shape.overlaps(
+                &Rect::from(actor_body),
+                wall_body.shape,
+                &Rect::from(wall_body)
+            ),
+            false
+        );
+    }
+
+    // Deflected downwards and around the wall
+    let actor_body = world.body(actor).unwrap();
+    assert_eq!(actor_body.pos.x > 70., true);
+    assert_eq!(actor_body.pos.y > 4., true);
+}
+
+#[test]
+fn round_bodies_are_blocked_by_rects() {
+    let mut world = new_default_world();
+
+    world.new_body(LAYER_WALL, P2::new(50., 0.), V2::new(20., 20.));
+    let actor = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(0., 0.),
+        V2::new(20., 20.),
+        V2::new(100., 0.),
+    );
+    world.body_mut(actor).unwrap().shape = Shape::Circle;
+
+    world.elapse_time(Duration::from_secs(1));
+    assert_eq_f32(world.body(actor).unwrap().pos.x, 30., 0.01);
+    assert_eq_f32(world.body(actor).unwrap().pos.y, 0., 0.01);
+}
+
+#[test]
+fn rects_are_blocked_by_round_bodies() {
+    let mut world = new_default_world();
+
+    let wall = world.new_body(LAYER_WALL, P2::new(50., 0.), V2::new(20., 20.));
+    world.body_mut(wall).unwrap().shape = Shape::Capsule;
+    let actor = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(0., 0.),
+        V2::new(20., 20.),
+        V2::new(100., 0.),
+    );
+
+    world.elapse_time(Duration::from_secs(1));
+    assert_eq_f32(world.body(actor).unwrap().pos.x, 30., 0.01);
+    assert_eq_f32(world.body(actor).unwrap().pos.y, 0., 0.01);
+}
+
+/// Like `fast_dynamic_body_does_not_tunnel_through_thin_wall`, but for every pairing of shapes
+/// involving a round one.
+#[test]
+fn fast_round_bodies_do_not_tunnel_through_thin_walls() {
+    let pairings = [
+        (Shape::Circle, Shape::Rect, V2::new(4., 60.)),
+        (Shape::Capsule, Shape::Rect, V2::new(4., 60.)),
+        (Shape::Rect, Shape::Circle, V2::new(4., 4.)),
+        (Shape::Rect, Shape::Capsule, V2::new(4., 60.)),
+        (Shape::Circle, Shape::Circle, V2::new(4., 4.)),
+        (Shape::Circle, Shape::Capsule, V2::new(4., 60.)),
+    ];
+    for &(actor_shape, wall_shape, wall_size) in pairings.iter() {
+        let mut world = new_default_world();
+
+        let wall_pos = P2::new(100., 5. - wall_size.y / 2.);
+        let wall = world.new_body(LAYER_WALL, wall_pos, wall_size);
+        world.body_mut(wall).unwrap().shape = wall_shape;
+        let actor = world.new_moving_body(
+            LAYER_ACTOR,
+            P2::new(0., 0.),
+            V2::new(10., 10.),
+            V2::new(12000., 0.), // 200px per 60fps step
+        );
+        world.body_mut(actor).unwrap().shape = actor_shape;
+
+        for _ in 0..5 {
+            world.elapse_time(Duration::from_millis(17));
+            let actor_body = world.body(actor).unwrap();
+            let wall_body = world.body(wall).unwrap();
+            assert_eq!(
+                actor_body.pos.x < 100.,
+                true,
+                "{:?} passed through {:?}",
+                actor_shape,
+                wall_shape
+            );
+            assert_eq!(
+                actor_body.shape.overlaps(
+                    &Rect::from(actor_body),
+                    wall_body.shape,
+                    &Rect::from(wall_body)
+                ),
+                false
+            );
+        }
+    }
+}
+
+#[test]
+fn fast_round_bodies_do_not_tunnel_through_tiles() {
+    let mut world = new_default_world();
+    world.set_tiles(TileLayer::new(
+        LAYER_WALL,
+        V2::new(20., 20.),
+        (10, 10),
+        |x, _| {
+            if x == 5 {
+                TileFlags::SOLID
+            } else {
+                TileFlags::EMPTY
+            }
+        },
+    ));
+
+    let actor = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(0., 20.),
+        V2::new(10., 10.),
+        V2::new(12000., 0.),
+    );
+    world.body_mut(actor).unwrap().shape = Shape::Circle;
+
+    world.elapse_time(Duration::from_millis(100));
+    assert_eq_f32(world.body(actor).unwrap().pos.x, 90., 0.01);
+}
+
+/// A 10x10 room of 20x20 tiles, walled in, with a pool of water in the middle
+fn new_tile_room() -> TileLayer {
+    TileLayer::new(LAYER_WALL, V2::new(20., 20.), (10, 10), |x, y| {
+        if x == 0 || y == 0 || x == 9 || y == 9 {
+            TileFlags::SOLID
+        } else if (4..6).contains(&x) && (4..6).contains(&y) {
+            TileFlags::WATER
+        } else {
+            TileFlags::EMPTY
+        }
+    })
+}
+
+#[test]
+fn bodies_are_blocked_by_tiles() {
+    let mut world = new_default_world();
+    world.set_tiles(new_tile_room());
+
+    let actor = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(100., 30.),
+        V2::new(10., 10.),
+        V2::new(0., -100.),
+    );
+
+    world.elapse_time(Duration::from_secs(1));
+    assert_eq!(world.body(actor).unwrap().pos, P2::new(100., 20.));
+}
+
+#[test]
+fn bodies_slide_along_tiles_without_snagging() {
+    let mut world = new_default_world();
+    world.set_tiles(new_tile_room());
+
+    // Pressing diagonally into the top wall, made up of many tiles, while sliding along it
+    let actor = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(20., 20.),
+        V2::new(10., 10.),
+        V2::new(100., -100.),
+    );
+
+    world.elapse_time(Duration::from_secs(1));
+    assert_eq_f32(world.body(actor).unwrap().pos.x, 120., 0.01);
+    assert_eq!(world.body(actor).unwrap().pos.y, 20.);
+}
+
+#[test]
+fn round_bodies_are_blocked_by_tiles() {
+    let mut world = new_default_world();
+    world.set_tiles(new_tile_room());
+
+    let actor = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(100., 30.),
+        V2::new(10., 10.),
+        V2::new(0., -100.),
+    );
+    world.body_mut(actor).unwrap().shape = Shape::Circle;
+
+    world.elapse_time(Duration::from_secs(1));
+    assert_eq_f32(world.body(actor).unwrap().pos.x, 100., 0.01);
+    assert_eq_f32(world.body(actor).unwrap().pos.y, 20., 0.01);
+}
+
+#[test]
+fn tiles_can_block_some_layers_but_not_others() {
+    let mut world = new_crowded_world();
+    let mut tiles = new_tile_room();
+    tiles.set_blocking(LAYER_ACTOR, TileFlags::SOLID | TileFlags::WATER);
+    world.set_tiles(tiles);
+
+    let actor = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(50., 85.),
+        V2::new(10., 10.),
+        V2::new(100., 0.),
+    );
+    let enemy = world.new_moving_body(
+        LAYER_ENEMY,
+        P2::new(50., 105.),
+        V2::new(10., 10.),
+        V2::new(100., 0.),
+    );
+
+    world.elapse_time(Duration::from_millis(500));
+    assert_eq!(world.body(actor).unwrap().pos, P2::new(70., 85.));
+    assert_eq_f32(world.body(enemy).unwrap().pos.x, 100., 1.);
+    assert_eq!(
+        world
+            .tiles()
+            .unwrap()
+            .flags_in(&Rect::from(world.body(enemy).unwrap())),
+        TileFlags::WATER
+    );
+}
+
+#[test]
+fn can_change_tiles_at_runtime() {
+    let mut world = new_default_world();
+    world.set_tiles(new_tile_room());
+
+    // Open a door in the top wall
+    world.set_tile(5, 0, TileFlags::EMPTY);
+    let actor = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(105., 30.),
+        V2::new(10., 10.),
+        V2::new(0., -100.),
+    );
+
+    world.elapse_time(Duration::from_millis(500));
+    assert_eq_f32(world.body(actor).unwrap().pos.y, -20., 1.);
+}
+
+#[test]
+fn raycasts_can_hit_tiles() {
+    let mut world = new_default_world();
+    world.set_tiles(new_tile_room());
+    let wall = world.new_body(LAYER_WALL, P2::new(100., 100.), V2::new(10., 10.));
+
+    let hit = world
+        .raycast(
+            Ray::new(P2::new(50., 30.), V2::new(0., -1.)),
+            1000.,
+            LAYER_WALL,
+        )
+        .unwrap();
+    assert_eq!(hit.body, None);
+    assert_eq!(hit.point, P2::new(50., 20.));
+
+    // Water doesn't block rays, but bodies in front of walls still do
+    let hit = world
+        .raycast(
+            Ray::new(P2::new(30., 105.), V2::new(1., 0.)),
+            1000.,
+            LAYER_WALL,
+        )
+        .unwrap();
+    assert_eq!(hit.body, Some(wall));
+
+    let hits = world.raycast_all(
+        Ray::new(P2::new(30., 105.), V2::new(1., 0.)),
+        1000.,
+        LAYER_WALL,
+    );
+    assert_eq!(hits.len(), 2);
+    assert_eq!(hits[1].body, None);
+    assert_eq!(hits[1].point, P2::new(180., 105.));
+
+    assert_eq!(
+        world.line_of_sight(P2::new(30., 30.), P2::new(30., 300.), LAYER_WALL),
+        false
+    );
+}
+
+#[test]
+fn one_way_bodies_only_block_from_some_sides() {
+    let mut world = new_default_world();
+
+    // A ledge you can hop down (moving +y) but can't climb back up
+    let ledge = world.new_body(LAYER_WALL, P2::new(0., 50.), V2::new(100., 10.));
+    world.body_mut(ledge).unwrap().blocks = RectSides::BOTTOM;
+
+    let hopper = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(10., 30.),
+        V2::new(10., 10.),
+        V2::new(0., 100.),
+    );
+    let climber = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(50., 70.),
+        V2::new(10., 10.),
+        V2::new(0., -100.),
+    );
+
+    world.elapse_time(Duration::from_millis(500));
+    assert_eq_f32(world.body(hopper).unwrap().pos.y, 80., 1.);
+    assert_eq!(world.body(climber).unwrap().pos.y, 60.);
+}
+
+#[test]
+fn one_way_bodies_block_round_bodies_too() {
+    let mut world = new_default_world();
+
+    let ledge = world.new_body(LAYER_WALL, P2::new(0., 50.), V2::new(100., 10.));
+    world.body_mut(ledge).unwrap().blocks = RectSides::BOTTOM;
+
+    let hopper = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(10., 30.),
+        V2::new(10., 10.),
+        V2::new(0., 100.),
+    );
+    world.body_mut(hopper).unwrap().shape = Shape::Circle;
+    let climber = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(50., 70.),
+        V2::new(10., 10.),
+        V2::new(0., -100.),
+    );
+    world.body_mut(climber).unwrap().shape = Shape::Circle;
+
+    world.elapse_time(Duration::from_millis(500));
+    assert_eq_f32(world.body(hopper).unwrap().pos.y, 80., 1.);
+    assert_eq_f32(world.body(climber).unwrap().pos.y, 60., 0.01);
+}
+
+#[test]
+fn ledge_tiles_can_be_hopped_down_but_not_climbed() {
+    let mut world = new_default_world();
+    // A row of ledges halfway down the room, with a gap on the left
+    world.set_tiles(TileLayer::new(
+        LAYER_WALL,
+        V2::new(20., 20.),
+        (10, 10),
+        |x, y| {
+            if y == 5 && x >= 3 {
+                TileFlags::LEDGE
+            } else {
+                TileFlags::EMPTY
+            }
+        },
+    ));
+
+    let hopper = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(80., 80.),
+        V2::new(10., 10.),
+        V2::new(0., 100.),
+    );
+    let climber = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(120., 130.),
+        V2::new(10., 10.),
+        V2::new(0., -100.),
+    );
+    let walker = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(20., 105.),
+        V2::new(10., 10.),
+        V2::new(100., 0.),
+    );
+
+    world.elapse_time(Duration::from_millis(500));
+    assert_eq_f32(world.body(hopper).unwrap().pos.y, 130., 1.);
+    assert_eq!(world.body(climber).unwrap().pos.y, 120.);
+    assert_eq!(world.body(walker).unwrap().pos.x, 50.);
+}
+
+#[test]
+fn one_way_layer_pairs_only_block_in_one_direction() {
+    let mut world = CollisionWorld::new(CollisionWorldParams {
+        one_way_layer_pairs: vec![[LAYER_ACTOR, LAYER_ENEMY]],
+        ..CollisionWorldParams::new(test_layers())
+    });
+
+    // Actors are blocked by enemies, but enemies walk right through actors
+    let actor = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(0., 0.),
+        V2::new(10., 10.),
+        V2::new(100., 0.),
+    );
+    world.new_body(LAYER_ENEMY, P2::new(30., 0.), V2::new(10., 10.));
+    let enemy = world.new_moving_body(
+        LAYER_ENEMY,
+        P2::new(30., 50.),
+        V2::new(10., 10.),
+        V2::new(-100., 0.),
+    );
+    world.new_body(LAYER_ACTOR, P2::new(0., 50.), V2::new(10., 10.));
+
+    world.elapse_time(Duration::from_millis(500));
+    assert_eq!(world.body(actor).unwrap().pos.x, 20.);
+    assert_eq_f32(world.body(enemy).unwrap().pos.x, -20., 1.);
+
+    // Contacts are still reported either way
+    let events: Vec<ContactEvent> = world.drain_events().collect();
+    assert_eq!(events.iter().any(|event| event.body == actor), true);
+    assert_eq!(events.iter().any(|event| event.body == enemy), true);
+}
+
+fn push_params() -> PushParams {
+    PushParams {
+        delay: Duration::from_millis(500),
+        distance: 20.,
+        speed: 40.,
+    }
+}
+
+#[test]
+#[should_panic(expected = "Invalid push params")]
+fn push_distance_must_be_positive() {
+    let mut world = new_default_world();
+    world.new_pushable(
+        LAYER_WALL,
+        P2::new(0., 0.),
+        V2::new(20., 20.),
+        PushParams {
+            distance: 0.,
+            ..push_params()
+        },
+    );
+}
+
+#[test]
+fn pushable_bodies_move_after_being_pushed_long_enough() {
+    let mut world = new_default_world();
+
+    let block = world.new_pushable(
+        LAYER_WALL,
+        P2::new(40., 0.),
+        V2::new(20., 20.),
+        push_params(),
+    );
+    let actor = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(20., 0.),
+        V2::new(20., 20.),
+        V2::new(50., 0.),
+    );
+
+    // Not pushed for long enough yet
+    world.elapse_time(Duration::from_millis(400));
+    assert_eq!(world.body(block).unwrap().pos, P2::new(40., 0.));
+    assert_eq!(world.body(actor).unwrap().pos, P2::new(20., 0.));
+
+    world.elapse_time(Duration::from_millis(200));
+    let block_x = world.body(block).unwrap().pos.x;
+    assert_eq!(block_x > 40. && block_x < 60., true);
+    assert_eq!(world.drain_settled().count(), 0);
+
+    world.body_mut(actor).unwrap().vel = V2::zero();
+    world.elapse_time(Duration::from_millis(500));
+    assert_eq!(world.body(block).unwrap().pos, P2::new(60., 0.));
+    assert_eq!(world.drain_settled().collect::<Vec<_>>(), vec![block]);
+}
+
+#[test]
+fn pushable_bodies_dont_move_if_pushing_stops_early() {
+    let mut world = new_default_world();
+
+    let block = world.new_pushable(
+        LAYER_WALL,
+        P2::new(40., 0.),
+        V2::new(20., 20.),
+        push_params(),
+    );
+    let actor = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(20., 0.),
+        V2::new(20., 20.),
+        V2::new(50., 0.),
+    );
+
+    world.elapse_time(Duration::from_millis(300));
+    world.body_mut(actor).unwrap().vel = V2::zero();
+    world.elapse_time(Duration::from_millis(100));
+    world.body_mut(actor).unwrap().vel = V2::new(50., 0.);
+    world.elapse_time(Duration::from_millis(300));
+
+    assert_eq!(world.body(block).unwrap().pos, P2::new(40., 0.));
+}
+
+#[test]
+fn pushable_bodies_snap_into_alignment() {
+    let mut world = new_default_world();
+
+    let block = world.new_pushable(
+        LAYER_WALL,
+        P2::new(3., 43.),
+        V2::new(20., 20.),
+        push_params(),
+    );
+    world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(3., 63.),
+        V2::new(20., 20.),
+        V2::new(0., -50.),
+    );
+
+    // Just long enough for a single push
+    world.elapse_time(Duration::from_millis(1200));
+    assert_eq!(world.body(block).unwrap().pos, P2::new(3., 20.));
+}
+
+#[test]
+fn pushable_bodies_are_blocked_by_walls() {
+    let mut world = CollisionWorld::new(CollisionWorldParams {
+        layer_pairs: vec![
+            [LAYER_WALL, LAYER_ACTOR],
+            [LAYER_WALL, LAYER_BLOCK],
+            [LAYER_ACTOR, LAYER_BLOCK],
+        ],
+        ..CollisionWorldParams::new(test_layers())
+    });
+    world.set_tiles(new_tile_room());
+
+    // Right up against the room's left wall
+    let block = world.new_pushable(
+        LAYER_BLOCK,
+        P2::new(20., 40.),
+        V2::new(20., 20.),
+        push_params(),
+    );
+    let actor = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(40., 40.),
+        V2::new(20., 20.),
+        V2::new(-50., 0.),
+    );
+
+    world.elapse_time(Duration::from_secs(2));
+    assert_eq!(world.body(block).unwrap().pos, P2::new(20., 40.));
+    assert_eq!(world.body(actor).unwrap().pos, P2::new(40., 40.));
+}
+
+/// A world where actors ride on platforms (in the block layer) without colliding with them, and
+/// enemies get shoved around by them
+fn new_platform_world() -> CollisionWorld {
+    CollisionWorld::new(CollisionWorldParams {
+        layer_pairs: vec![
+            [LAYER_WALL, LAYER_ACTOR],
+            [LAYER_WALL, LAYER_ENEMY],
+            [LAYER_BLOCK, LAYER_ENEMY],
+        ],
+        ..CollisionWorldParams::new(test_layers())
+    })
+}
+
+fn platform_params(path: Vec<P2>) -> KinematicParams {
+    KinematicParams {
+        carries: LAYER_ACTOR.into(),
+        surface_vel: V2::zero(),
+        path,
+        path_speed: 40.,
+    }
+}
+
+#[test]
+#[should_panic(expected = "Invalid kinematic params")]
+fn kinematic_path_speed_must_be_positive() {
+    let mut world = new_platform_world();
+    world.new_kinematic(
+        LAYER_BLOCK,
+        P2::new(0., 0.),
+        V2::new(40., 40.),
+        KinematicParams {
+            path_speed: 0.,
+            ..platform_params(vec![P2::new(0., 0.), P2::new(100., 0.)])
+        },
+    );
+}
+
+#[test]
+fn kinematic_bodies_loop_along_their_path() {
+    let mut world = new_platform_world();
+    let platform = world.new_kinematic(
+        LAYER_BLOCK,
+        P2::new(0., 0.),
+        V2::new(40., 40.),
+        platform_params(vec![P2::new(0., 0.), P2::new(20., 0.)]),
+    );
+
+    world.elapse_time(Duration::from_millis(250));
+    assert_eq_f32(world.body(platform).unwrap().pos.x, 10., 0.1);
+
+    // Turning around at the end of the path
+    world.elapse_time(Duration::from_millis(500));
+    assert_eq_f32(world.body(platform).unwrap().pos.x, 10., 0.1);
+
+    world.elapse_time(Duration::from_millis(250));
+    assert_eq_f32(world.body(platform).unwrap().pos.x, 0., 0.1);
+}
+
+#[test]
+fn kinematic_bodies_carry_their_riders() {
+    let mut world = new_platform_world();
+    let platform = world.new_kinematic(
+        LAYER_BLOCK,
+        P2::new(0., 0.),
+        V2::new(40., 40.),
+        platform_params(vec![P2::new(0., 0.), P2::new(100., 0.)]),
+    );
+    let rider = world.new_body(LAYER_ACTOR, P2::new(15., 15.), V2::new(10., 10.));
+    // Not standing on the platform
+    let bystander = world.new_body(LAYER_ACTOR, P2::new(35., 50.), V2::new(10., 10.));
+
+    world.elapse_time(Duration::from_secs(1));
+    assert_eq_f32(world.body(platform).unwrap().pos.x, 40., 0.1);
+    assert_eq_f32(world.body(rider).unwrap().pos.x, 55., 0.1);
+    assert_eq!(world.body(bystander).unwrap().pos, P2::new(35., 50.));
+}
+
+///       +---+
+///       |   |
+///  +----|---|----+         +-------------+
+///  | +-+|   |    |  ====>  |      +-+    |
+///  | | ||   |    |         |      | |    |
+///  | +-+|   |    |         |      +-+    |
+///  +----|---|----+         +-------------+
+///       |   |
+///       +---+
+///
+/// Riders carried into a wall stop there, and the platform slides out from under them.
+#[test]
+fn kinematic_riders_are_blocked_by_walls() {
+    let mut world = new_platform_world();
+    let platform = world.new_kinematic(
+        LAYER_BLOCK,
+        P2::new(0., 0.),
+        V2::new(40., 20.),
+        platform_params(Vec::new()),
+    );
+    world.body_mut(platform).unwrap().vel = V2::new(40., 0.);
+    world.new_body(LAYER_WALL, P2::new(30., -20.), V2::new(20., 60.));
+    let rider = world.new_body(LAYER_ACTOR, P2::new(5., 5.), V2::new(10., 10.));
+
+    world.elapse_time(Duration::from_secs(2));
+    assert_eq_f32(world.body(platform).unwrap().pos.x, 80., 0.1);
+    assert_eq_f32(world.body(rider).unwrap().pos.x, 20., 0.1);
+}
+
+#[test]
+fn kinematic_bodies_move_riders_by_their_surface_velocity() {
+    let mut world = new_platform_world();
+    let belt = world.new_kinematic(
+        LAYER_BLOCK,
+        P2::new(0., 0.),
+        V2::new(100., 20.),
+        KinematicParams {
+            surface_vel: V2::new(30., 0.),
+            ..platform_params(Vec::new())
+        },
+    );
+    let rider = world.new_body(LAYER_ACTOR, P2::new(5., 5.), V2::new(10., 10.));
+
+    world.elapse_time(Duration::from_secs(1));
+    assert_eq!(world.body(belt).unwrap().pos, P2::new(0., 0.));
+    assert_eq_f32(world.body(rider).unwrap().pos.x, 35., 0.1);
+}
+
+#[test]
+fn kinematic_bodies_push_bodies_out_of_their_way() {
+    let mut world = new_platform_world();
+    let platform = world.new_kinematic(
+        LAYER_BLOCK,
+        P2::new(0., 0.),
+        V2::new(20., 20.),
+        platform_params(Vec::new()),
+    );
+    world.body_mut(platform).unwrap().vel = V2::new(40., 0.);
+    let enemy = world.new_body(LAYER_ENEMY, P2::new(30., 0.), V2::new(20., 20.));
+    let wall = world.new_body(LAYER_WALL, P2::new(120., 0.), V2::new(20., 20.));
+
+    world.elapse_time(Duration::from_secs(1));
+    assert_eq_f32(world.body(platform).unwrap().pos.x, 40., 0.1);
+    assert_eq_f32(world.body(enemy).unwrap().pos.x, 60., 0.1);
+
+    // Nothing stops a kinematic body, but a wall still stops what it's pushing, which gets
+    // squeezed out sideways instead of ending up inside the kinematic body
+    for _ in 0..75 {
+        world.elapse_time(Duration::from_micros(16666));
+        assert_eq!(overlaps(&world, platform, enemy), false);
+    }
+    assert_eq_f32(world.body(platform).unwrap().pos.x, 90., 0.1);
+    assert_eq!(world.body(enemy).unwrap().pos.x, 100.);
+    assert_eq!(world.body(enemy).unwrap().pos.y.abs(), 20.);
+    assert_eq!(world.body(wall).unwrap().pos.x, 120.);
+    assert_eq!(world.drain_crushed().count(), 0);
+}
+
+/// The same as `kinematic_bodies_push_bodies_out_of_their_way`, but with no room to squeeze out
+#[test]
+fn kinematic_bodies_crush_bodies_with_nowhere_to_go() {
+    let mut world = new_platform_world();
+    let platform = world.new_kinematic(
+        LAYER_BLOCK,
+        P2::new(60., 0.),
+        V2::new(20., 20.),
+        platform_params(Vec::new()),
+    );
+    world.body_mut(platform).unwrap().vel = V2::new(40., 0.);
+    let enemy = world.new_body(LAYER_ENEMY, P2::new(100., 0.), V2::new(20., 20.));
+    world.new_body(LAYER_WALL, P2::new(120., 0.), V2::new(20., 20.));
+    world.new_body(LAYER_WALL, P2::new(0., -20.), V2::new(140., 20.));
+    world.new_body(LAYER_WALL, P2::new(0., 20.), V2::new(140., 20.));
+
+    let mut crushed = Vec::new();
+    for _ in 0..60 {
+        world.elapse_time(Duration::from_micros(16666));
+        crushed.extend(world.drain_crushed());
+        assert_eq!(overlaps(&world, platform, enemy), false);
+    }
+    assert_eq!(crushed, vec![enemy]);
+    // Left behind the platform, as that was the only place it could fit
+    assert_eq!(
+        world.body(enemy).unwrap().pos.x < world.body(platform).unwrap().pos.x,
+        true
+    );
+}
+
+fn overlaps(world: &CollisionWorld, a: BodyHandle, b: BodyHandle) -> bool {
+    let (body_a, body_b) = (world.body(a).unwrap(), world.body(b).unwrap());
+    body_a
+        .shape
+        .overlaps(&Rect::from(body_a), body_b.shape, &Rect::from(body_b))
+}
+
+#[test]
+#[should_panic(expected = "Invalid corner tolerance: -1")]
+fn corner_tolerance_cant_be_negative() {
+    CollisionWorld::new(CollisionWorldParams {
+        corner_tolerance: -1.,
+        ..CollisionWorldParams::new(test_layers())
+    });
+}
+
+/// A world with a wall running down it at x = 50, with a doorway in it from y = 20 to
+/// y = 20 + `door_height`
+fn new_doorway_world(corner_tolerance: f32, door_height: f32) -> CollisionWorld {
+    let mut world = CollisionWorld::new(CollisionWorldParams {
+        layer_pairs: vec![[LAYER_WALL, LAYER_ACTOR]],
+        corner_tolerance,
+        ..CollisionWorldParams::new(test_layers())
+    });
+    world.new_body(LAYER_WALL, P2::new(50., 0.), V2::new(10., 20.));
+    world.new_body(
+        LAYER_WALL,
+        P2::new(50., 20. + door_height),
+        V2::new(10., 20.),
+    );
+    world
+}
+
+#[test]
+fn bodies_are_nudged_around_corners() {
+    let mut world = new_doorway_world(3., 20.);
+
+    // Catching the top of the lower wall
+    let actor_low = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(20., 22.),
+        V2::new(20., 20.),
+        V2::new(100., 0.),
+    );
+    world.elapse_time(Duration::from_secs(1));
+    assert_eq!(world.body(actor_low).unwrap().pos.y, 20.);
+    assert_eq!(world.body(actor_low).unwrap().pos.x > 60., true);
+
+    // Catching the bottom of the upper wall, from the other side
+    let actor_high = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(80., 17.),
+        V2::new(20., 20.),
+        V2::new(-100., 0.),
+    );
+    world.elapse_time(Duration::from_secs(1));
+    assert_eq!(world.body(actor_high).unwrap().pos.y, 20.);
+    assert_eq!(world.body(actor_high).unwrap().pos.x < 30., true);
+}
+
+#[test]
+fn bodies_arent_nudged_if_too_far_from_corner() {
+    let mut world = new_doorway_world(3., 20.);
+
+    let actor = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(20., 25.),
+        V2::new(20., 20.),
+        V2::new(100., 0.),
+    );
+    world.elapse_time(Duration::from_secs(1));
+    assert_eq!(world.body(actor).unwrap().pos, P2::new(30., 25.));
+}
+
+#[test]
+fn bodies_arent_nudged_into_gaps_too_small_for_them() {
+    let mut world = new_doorway_world(3., 19.);
+
+    let actor = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(20., 21.),
+        V2::new(20., 20.),
+        V2::new(100., 0.),
+    );
+    world.elapse_time(Duration::from_secs(1));
+    assert_eq!(world.body(actor).unwrap().pos, P2::new(30., 21.));
+}
+
+#[test]
+fn bodies_arent_nudged_if_corner_correction_disabled() {
+    let mut world = new_doorway_world(0., 20.);
+
+    let actor = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(20., 22.),
+        V2::new(20., 20.),
+        V2::new(100., 0.),
+    );
+    world.elapse_time(Duration::from_secs(1));
+    assert_eq!(world.body(actor).unwrap().pos, P2::new(30., 22.));
+}
+
+/// A world with nothing to collide with which steps every 10ms
+fn new_fixed_step_world() -> CollisionWorld {
+    CollisionWorld::new(CollisionWorldParams {
+        time_step: Duration::from_millis(10),
+        ..CollisionWorldParams::new(test_layers())
+    })
+}
+
+#[test]
+fn bodies_move_in_whole_time_steps() {
+    let mut world = new_fixed_step_world();
+    let actor = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(0., 0.),
+        V2::new(10., 10.),
+        V2::new(100., 0.),
+    );
+
+    world.elapse_time(Duration::from_millis(25));
+    assert_eq_f32(world.body(actor).unwrap().pos.x, 2., 0.001);
+    assert_eq_f32(world.interpolation_alpha(), 0.5, 0.001);
+
+    // The leftover time carries over into the next call
+    world.elapse_time(Duration::from_millis(5));
+    assert_eq_f32(world.body(actor).unwrap().pos.x, 3., 0.001);
+    assert_eq_f32(world.interpolation_alpha(), 0., 0.001);
+}
+
+#[test]
+fn bodies_remember_where_they_were_last_step() {
+    let mut world = new_fixed_step_world();
+    let actor = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(0., 0.),
+        V2::new(10., 10.),
+        V2::new(100., 0.),
+    );
+    let wall = world.new_body(LAYER_WALL, P2::new(50., 0.), V2::new(10., 10.));
+    assert_eq!(world.body(actor).unwrap().prev_pos, P2::new(0., 0.));
+
+    world.elapse_time(Duration::from_millis(35));
+    let body = world.body(actor).unwrap();
+    assert_eq_f32(body.prev_pos.x, 2., 0.001);
+    assert_eq_f32(body.pos.x, 3., 0.001);
+    assert_eq_f32(
+        body.interpolated_pos(world.interpolation_alpha()).x,
+        2.5,
+        0.001,
+    );
+
+    // Bodies which don't move stay put
+    let body = world.body(wall).unwrap();
+    assert_eq!(body.interpolated_pos(0.5), P2::new(50., 0.));
+}
+
+#[test]
+fn bodies_moved_by_hand_dont_interpolate_from_their_old_spot() {
+    let mut world = new_fixed_step_world();
+    let wall = world.new_body(LAYER_WALL, P2::new(0., 0.), V2::new(10., 10.));
+
+    world.body_mut(wall).unwrap().pos = P2::new(100., 0.);
+    world.elapse_time(Duration::from_millis(15));
+
+    let body = world.body(wall).unwrap();
+    assert_eq!(body.prev_pos, P2::new(100., 0.));
+    assert_eq!(body.interpolated_pos(0.5), P2::new(100., 0.));
+}
+
+/// +-------+     +-------+           +-----+-------+
+/// |       |     |       |           |     |       |
+/// |       | ←←← |       |  ======>  | ←←← |       |
+/// |       |     |       |           |     |       |
+/// +-------+     +-------+           +-----+-------+
+#[test]
+fn bodies_only_collide_if_layers_are_registered_to_collide() {
+    let mut world = CollisionWorld::new(CollisionWorldParams {
+        partition_size: [40., 40.],
+        ..CollisionWorldParams::new(test_layers())
+    });
+
+    let wall = world.new_body(LAYER_WALL, P2::new(0., 0.), V2::new(20., 20.));
+    let actor = world.new_moving_body(
+        LAYER_ACTOR,
+        P2::new(30., 0.),
+        V2::new(20., 20.),
+        V2::new(-1., 0.),
+    );
+
+    world.elapse_time(Duration::from_secs(20));
+
+    // Player passed through the wall
+    assert_eq_f32(world.body(actor).unwrap().pos.x, 10., 0.1);
+
+    // The wall didn't budge
+    assert_eq_f32(world.body(wall).unwrap().pos.x, 0., 0.1);
+}
+
+#[test]
+fn bodies_in_several_layers_collide_with_all_of_them() {
+    let mut world = new_crowded_world();
+    let ghost = world.new_moving_body(
+        LAYER_PASSTHRU | LAYER_ENEMY,
+        P2::new(30., 0.),
+        V2::new(20., 20.),
+        V2::new(-100., 0.),
+    );
+    world.new_body(LAYER_WALL, P2::new(0., 0.), V2::new(20., 20.));
+
+    world.elapse_time(Duration::from_secs(1));
+    assert_eq!(world.body(ghost).unwrap().pos, P2::new(20., 0.));
+
+    // Layers can be changed after the fact
+    world.body_mut(ghost).unwrap().layers = LAYER_PASSTHRU.into();
+    world.elapse_time(Duration::from_secs(1));
+    assert_eq!(world.body(ghost).unwrap().pos.x < 0., true);
+}
+
+#[test]
+#[should_panic(expected = "Invalid body layers: LayerMask(32)")]
+fn bodies_must_belong_to_registered_layers() {
+    let mut world = new_default_world();
+    world.new_body(Layer::new(5), P2::new(0., 0.), V2::new(20., 20.));
+}
+
+#[test]
+#[should_panic(expected = "Invalid body layers: LayerMask(0)")]
+fn bodies_must_belong_to_at_least_one_layer() {
+    let mut world = new_default_world();
+    world.new_body(LayerMask::NONE, P2::new(0., 0.), V2::new(20., 20.));
+}
+
+#[test]
+#[should_panic(expected = "Unregistered layer in pair: [Layer(0), Layer(5)]")]
+fn layer_pairs_must_be_registered() {
+    CollisionWorld::new(CollisionWorldParams {
+        layer_pairs: vec![[LAYER_WALL, Layer::new(5)]],
+        ..CollisionWorldParams::new(test_layers())
+    });
+}
+
+#[test]
+fn layers_can_be_looked_up_by_name() {
+    let world = new_default_world();
+    assert_eq!(world.layers().get("actor"), Some(LAYER_ACTOR));
+    assert_eq!(world.layers().name(LAYER_WALL), Some("wall"));
+}
+
+/// A walled room with an actor chasing an enemy into a corner and a third body stuck in place
+fn new_snapshot_world() -> (CollisionWorld, Vec<BodyHandle>) {
+    let mut world = new_crowded_world();
+    world.set_tiles(new_tile_room());
+
+    let handles = vec![
+        world.new_moving_body(
+            LAYER_ACTOR,
+            P2::new(30., 30.),
+            V2::new(10., 10.),
+            V2::new(60., 40.),
+        ),
+        world.new_moving_body(
+            LAYER_ENEMY,
+            P2::new(50., 40.),
+            V2::new(10., 10.),
+            V2::new(50., 50.),
+        ),
+        world.new_body(LAYER_WALL, P2::new(120., 40.), V2::new(20., 20.)),
+    ];
+    (world, handles)
+}
+
+fn positions(world: &CollisionWorld, handles: &[BodyHandle]) -> Vec<Option<P2>> {
+    handles
+        .iter()
+        .map(|handle| world.body(*handle).map(|body| body.pos))
+        .collect()
+}
+
+#[test]
+fn world_can_be_restored_from_snapshot() {
+    let (mut world, handles) = new_snapshot_world();
+    world.elapse_time(Duration::from_millis(510));
+    let snapshot = world.snapshot();
+    let positions_then = positions(&world, &handles);
+    let alpha_then = world.interpolation_alpha();
+
+    world.elapse_time(Duration::from_secs(2));
+    let positions_later = positions(&world, &handles);
+    let events_later: Vec<ContactEvent> = world.drain_events().collect();
+    assert_ne!(positions_later, positions_then);
+
+    world.restore(&snapshot);
+    assert_eq!(positions(&world, &handles), positions_then);
+    assert_eq!(world.interpolation_alpha(), alpha_then);
+
+    // Replaying the same time from the same state ends up in exactly the same place
+    world.elapse_time(Duration::from_secs(2));
+    assert_eq!(positions(&world, &handles), positions_later);
+    assert_eq!(world.drain_events().collect::<Vec<_>>(), events_later);
+}
+
+#[test]
+fn restoring_a_snapshot_restores_handles() {
+    let (mut world, handles) = new_snapshot_world();
+    let snapshot = world.snapshot();
+
+    world.remove_body(handles[2]);
+    let new_body = world.new_body(LAYER_WALL, P2::new(0., 0.), V2::new(10., 10.));
+    world.set_tile(1, 1, TileFlags::SOLID);
+
+    world.restore(&snapshot);
+    assert_eq!(world.body(handles[2]).unwrap().pos, P2::new(120., 40.));
+    assert_eq!(world.body(new_body).is_none(), true);
+    assert_eq!(
+        world.query_rect(Rect::new(P2::new(120., 40.), V2::new(1., 1.)), LAYER_WALL),
+        vec![handles[2]]
+    );
+    assert_eq!(world.tiles().unwrap().get(1, 1), TileFlags::EMPTY);
+}
+
+#[test]
+fn pending_changes_survive_a_snapshot() {
+    let (mut world, handles) = new_snapshot_world();
+
+    // Bodies changed by hand are only picked up on the next call to `elapse_time`
+    world.body_mut(handles[2]).unwrap().vel = V2::new(0., 100.);
+    let snapshot = world.snapshot();
+
+    world.elapse_time(Duration::from_millis(100));
+    let positions_later = positions(&world, &handles);
+
+    world.restore(&snapshot);
+    world.elapse_time(Duration::from_millis(100));
+    assert_eq!(positions(&world, &handles), positions_later);
+}
+
+#[test]
+#[should_panic(expected = "Snapshot doesn't match world")]
+fn snapshots_only_restore_into_matching_worlds() {
+    let snapshot = new_default_world().snapshot();
+    let mut world = CollisionWorld::new(CollisionWorldParams {
+        partition_size: [40., 40.],
+        ..CollisionWorldParams::new(test_layers())
+    });
+    world.restore(&snapshot);
+}
+
+#[test]
+fn handles_can_be_rebound_into_other_worlds() {
+    let (world, handles) = new_snapshot_world();
+    let (mut world_other, handles_other) = new_snapshot_world();
+
+    // Each world only accepts its own handles, but can find the body in the same place
+    assert_eq!(positions(&world_other, &handles), vec![None, None, None]);
+    let rebound: Vec<_> = handles.iter().map(|&h| world_other.rebind(h)).collect();
+    assert_eq!(
+        rebound,
+        handles_other.iter().copied().map(Some).collect::<Vec<_>>()
+    );
+    assert_eq!(world.rebind(handles[0]), Some(handles[0]));
+
+    // Only as long as it's still there
+    world_other.remove_body(handles_other[1]);
+    world_other.new_body(LAYER_WALL, P2::new(0., 0.), V2::new(10., 10.));
+    assert_eq!(world_other.rebind(handles[1]), None);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn saved_handles_can_be_rebound() {
+    let (world, handles) = new_snapshot_world();
+    let json = serde_json::to_string(&handles).unwrap();
+    let handles_loaded: Vec<BodyHandle> = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(positions(&world, &handles_loaded), vec![None, None, None]);
+    let rebound: Vec<_> = handles_loaded.iter().map(|&h| world.rebind(h)).collect();
+    assert_eq!(
+        rebound,
+        handles.iter().copied().map(Some).collect::<Vec<_>>()
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn snapshots_can_be_serialized() {
+    let new_tiles = || {
+        let mut tiles = new_tile_room();
+        tiles.set_blocking(LAYER_ACTOR, TileFlags::SOLID | TileFlags::WATER);
+        tiles.set_blocking(LAYER_ENEMY, TileFlags::SOLID);
+        tiles.set_blocking(LAYER_BLOCK, TileFlags::WATER);
+        tiles
+    };
+    // The same tiles always serialize the same way, however they were put together
+    let tiles_json = serde_json::to_string(&new_tiles()).unwrap();
+    for _ in 0..10 {
+        assert_eq!(serde_json::to_string(&new_tiles()).unwrap(), tiles_json);
+    }
+
+    let (mut world, handles) = new_snapshot_world();
+    world.set_tiles(new_tiles());
+    world.elapse_time(Duration::from_millis(510));
+    let json = serde_json::to_string(&world.snapshot()).unwrap();
+    let handles_json = serde_json::to_string(&handles).unwrap();
+
+    // The loaded world only gets its bodies from the snapshot, and accepts the handles saved
+    // along with it once they're rebound
+    let snapshot: game2d::collide::snapshot::WorldSnapshot = serde_json::from_str(&json).unwrap();
+    let handles_saved: Vec<BodyHandle> = serde_json::from_str(&handles_json).unwrap();
+    let mut world_loaded = new_crowded_world();
+    world_loaded.restore(&snapshot);
+    let handles_loaded: Vec<_> = handles_saved
+        .iter()
+        .map(|&handle| world_loaded.rebind(handle).unwrap())
+        .collect();
+    assert_eq!(
+        positions(&world_loaded, &handles_loaded),
+        positions(&world, &handles)
+    );
+    assert_eq!(
+        world_loaded.interpolation_alpha(),
+        world.interpolation_alpha()
+    );
+    assert_eq!(
+        world_loaded.state_hash(),
+        world.state_hash(),
+        "Restored world should be identical to the original"
+    );
+
+    // Nothing is lost on the way, so it serializes back into exactly the same thing
+    assert_eq!(serde_json::to_string(&snapshot).unwrap(), json);
+}
+
+/// A room full of actors and enemies all trying to squeeze into the same spot, where the order
+/// they move in decides who gets there first
+fn new_contested_world() -> (CollisionWorld, Vec<BodyHandle>) {
+    let mut world = new_crowded_world();
+    world.set_tiles(new_tile_room());
+
+    let mut handles = Vec::new();
+    for i in 0..12 {
+        let layer = if i % 3 == 0 { LAYER_ACTOR } else { LAYER_ENEMY };
+        let pos = P2::new(25. + (i % 4) as f32 * 40., 25. + (i / 4) as f32 * 50.);
+        handles.push(world.new_moving_body(layer, pos, V2::new(12., 12.), V2::zero()));
+    }
+    (world, handles)
+}
+
+/// Everyone heads for the middle of the room, changing course every so often
+fn new_contested_replay(handles: &[BodyHandle]) -> Replay {
+    let mut replay = Replay::new();
+    for frame in 0..90 {
+        let velocities = if frame % 30 == 0 {
+            handles
+                .iter()
+                .enumerate()
+                .map(|(i, handle)| {
+                    let angle = (i * 7 + frame) as f32;
+                    let vel = V2::new(angle.cos(), angle.sin()) * 20. + V2::new(40., 30.);
+                    (*handle, vel)
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        replay.record(velocities, Duration::from_millis(25));
+    }
+    replay
+}
+
+#[test]
+fn worlds_given_the_same_inputs_end_up_identical() {
+    let (_, handles) = new_contested_world();
+    let replay = new_contested_replay(&handles);
+    let hashes: Vec<Vec<u64>> = (0..5)
+        .map(|_| replay.play(&mut new_contested_world().0))
+        .collect();
+
+    assert_eq!(hashes[0].len(), 90);
+    for other_hashes in hashes[1..].iter() {
+        assert_eq!(other_hashes, &hashes[0]);
+    }
+}
+
+#[test]
+#[should_panic(expected = "Replay doesn't match world: no body for")]
+fn replays_only_play_into_matching_worlds() {
+    let (_, handles) = new_contested_world();
+    let replay = new_contested_replay(&handles);
+    let (mut world, handles) = new_contested_world();
+    world.remove_body(handles[3]);
+    replay.play(&mut world);
+}
+
+#[test]
+fn state_hash_reflects_changes_to_the_world() {
+    let (mut world, handles) = new_contested_world();
+    let hash = world.state_hash();
+    assert_eq!(world.state_hash(), hash);
+
+    world.body_mut(handles[0]).unwrap().vel = V2::new(10., 0.);
+    assert_ne!(world.state_hash(), hash);
+
+    world.elapse_time(Duration::from_millis(5)); // Less than a full step
+    let hash = world.state_hash();
+    world.elapse_time(Duration::from_millis(5));
+    assert_ne!(world.state_hash(), hash);
+}
+
+#[test]
+fn replays_can_be_played_back_from_a_snapshot() {
+    let (mut world, handles) = new_contested_world();
+    let replay = new_contested_replay(&handles);
+    world.elapse_time(Duration::from_millis(100));
+    let snapshot = world.snapshot();
+    let hashes = replay.play(&mut world);
+
+    // Bodies created after the snapshot don't change anything once it's restored
+    world.new_body(LAYER_WALL, P2::new(100., 100.), V2::new(10., 10.));
+    world.restore(&snapshot);
+    assert_eq!(replay.play(&mut world), hashes);
 }
 
 #[test]
@@ -391,7 +2449,8 @@ fn partitioning_the_board_optimizes_collision_performance() {
     fn create_world(partition_size: [f32; 2]) -> CollisionWorld {
         let mut world = CollisionWorld::new(CollisionWorldParams {
             partition_size,
-            group_pairs: vec![[GROUP_WALL, GROUP_ACTOR]],
+            layer_pairs: vec![[LAYER_WALL, LAYER_ACTOR]],
+            ..CollisionWorldParams::new(test_layers())
         });
 
         let x_squares = 100;
@@ -401,19 +2460,19 @@ fn partitioning_the_board_optimizes_collision_performance() {
 
         for x in 0..=x_squares {
             let x = x as f32;
-            world.new_body(GROUP_WALL, P2::new(x * body_size.x, 0.), body_size);
+            world.new_body(LAYER_WALL, P2::new(x * body_size.x, 0.), body_size);
         }
 
         for y in 1..y_squares {
             let y = y as f32;
-            world.new_body(GROUP_WALL, P2::new(0., y * body_size.y), body_size);
+            world.new_body(LAYER_WALL, P2::new(0., y * body_size.y), body_size);
         }
 
         for y in 1..y_squares {
             let y = y as f32;
             let x_squares = x_squares as f32;
             world.new_body(
-                GROUP_WALL,
+                LAYER_WALL,
                 P2::new(x_squares * body_size.x, y * body_size.y),
                 body_size,
             );
@@ -423,7 +2482,7 @@ fn partitioning_the_board_optimizes_collision_performance() {
             let x = x as f32;
             let y_squares = y_squares as f32;
             world.new_body(
-                GROUP_WALL,
+                LAYER_WALL,
                 P2::new(x * body_size.x, y_squares * body_size.y),
                 body_size,
             );
@@ -434,7 +2493,7 @@ fn partitioning_the_board_optimizes_collision_performance() {
                 let x = x as f32;
                 let y = y as f32;
                 world.new_body(
-                    GROUP_WALL,
+                    LAYER_WALL,
                     P2::new(x * body_size.x, y * body_size.y),
                     body_size,
                 );
@@ -447,7 +2506,7 @@ fn partitioning_the_board_optimizes_collision_performance() {
                 let x = x as f32;
                 let y = y as f32;
                 world.new_body(
-                    GROUP_PASSTHRU,
+                    LAYER_PASSTHRU,
                     P2::new(x * body_size.x, y * body_size.y),
                     body_size,
                 );
@@ -455,7 +2514,7 @@ fn partitioning_the_board_optimizes_collision_performance() {
         }
 
         world.new_moving_body(
-            GROUP_ACTOR,
+            LAYER_ACTOR,
             P2::new(body_size.x, body_size.y),
             body_size,
             actor_vel,
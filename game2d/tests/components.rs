@@ -0,0 +1,254 @@
+use game2d::components::{
+    join, join3, join3_mut, join_mut, Column, DenseColumn, Entities, SparseColumn,
+};
+use game2d::geom::P2;
+
+#[test]
+fn can_add_and_remove_components() {
+    let mut entities = Entities::new();
+    let mut positions: DenseColumn<P2> = DenseColumn::new();
+    let mut health: SparseColumn<i32> = SparseColumn::new();
+
+    let player = entities.spawn();
+    let wall = entities.spawn();
+    assert_eq!(entities.len(), 2);
+
+    assert_eq!(positions.insert(player, P2::new(1., 2.)), None);
+    assert_eq!(positions.insert(wall, P2::new(3., 4.)), None);
+    assert_eq!(health.insert(player, 10), None);
+    assert_eq!(positions.len(), 2);
+    assert_eq!(health.len(), 1);
+
+    assert_eq!(positions.get(player), Some(&P2::new(1., 2.)));
+    assert_eq!(health.get(player), Some(&10));
+    assert_eq!(health.get(wall), None);
+    assert_eq!(health.contains(wall), false);
+
+    // Inserting again replaces the old component
+    assert_eq!(health.insert(player, 5), Some(10));
+    *health.get_mut(player).unwrap() -= 1;
+    assert_eq!(health.get(player), Some(&4));
+    assert_eq!(health.len(), 1);
+
+    assert_eq!(positions.remove(wall), Some(P2::new(3., 4.)));
+    assert_eq!(positions.remove(wall), None);
+    assert_eq!(positions.get(wall), None);
+    assert_eq!(positions.len(), 1);
+    assert_eq!(entities.contains(wall), true);
+}
+
+#[test]
+fn sparse_columns_stay_consistent_after_removals() {
+    let mut entities = Entities::new();
+    let mut health: SparseColumn<usize> = SparseColumn::new();
+
+    let handles: Vec<_> = (0..5).map(|_| entities.spawn()).collect();
+    for (i, &entity) in handles.iter().enumerate() {
+        health.insert(entity, i);
+    }
+
+    assert_eq!(health.remove(handles[0]), Some(0));
+    assert_eq!(health.remove(handles[3]), Some(3));
+    assert_eq!(health.len(), 3);
+    for &i in [1, 2, 4].iter() {
+        assert_eq!(health.get(handles[i]), Some(&i));
+    }
+
+    let mut remaining: Vec<_> = health.iter().map(|(_, &value)| value).collect();
+    remaining.sort();
+    assert_eq!(remaining, vec![1, 2, 4]);
+}
+
+#[test]
+fn despawned_entities_lose_their_components() {
+    let mut entities = Entities::new();
+    let mut positions: DenseColumn<P2> = DenseColumn::new();
+    let mut health: SparseColumn<i32> = SparseColumn::new();
+
+    let enemy = entities.spawn();
+    positions.insert(enemy, P2::new(1., 1.));
+    health.insert(enemy, 3);
+
+    assert_eq!(
+        entities.despawn(enemy, &mut [&mut positions, &mut health]),
+        true
+    );
+    assert_eq!(
+        entities.despawn(enemy, &mut [&mut positions, &mut health]),
+        false
+    );
+    assert_eq!(entities.contains(enemy), false);
+    assert_eq!(entities.is_empty(), true);
+    assert_eq!(positions.is_empty(), true);
+    assert_eq!(health.is_empty(), true);
+
+    // A new entity in the same slot doesn't see anything through the old handle, and vice versa
+    let other = entities.spawn();
+    health.insert(other, 7);
+    assert_eq!(health.get(enemy), None);
+    assert_eq!(health.remove(enemy), None);
+    assert_eq!(health.get(other), Some(&7));
+    assert_eq!(entities.iter().collect::<Vec<_>>(), vec![other]);
+}
+
+#[test]
+fn leftover_components_are_overwritten() {
+    let mut entities = Entities::new();
+    let mut positions: DenseColumn<P2> = DenseColumn::new();
+    let mut health: SparseColumn<i32> = SparseColumn::new();
+
+    let enemy = entities.spawn();
+    positions.insert(enemy, P2::new(1., 1.));
+    health.insert(enemy, 3);
+    // Despawned without passing in the columns, but joins still skip it
+    entities.despawn(enemy, &mut []);
+    assert_eq!(join(&entities, &positions, &health).count(), 0);
+    assert_eq!(join_mut(&entities, &mut health, &positions).count(), 0);
+
+    let other = entities.spawn();
+    assert_eq!(positions.get(other), None);
+    assert_eq!(health.get(other), None);
+    assert_eq!(positions.insert(other, P2::new(2., 2.)), None);
+    assert_eq!(health.insert(other, 5), None);
+    assert_eq!(positions.len(), 1);
+    assert_eq!(health.len(), 1);
+    assert_eq!(positions.get(enemy), None);
+    assert_eq!(health.get(other), Some(&5));
+}
+
+#[test]
+fn can_join_columns() {
+    let mut entities = Entities::new();
+    let mut positions: DenseColumn<P2> = DenseColumn::new();
+    let mut sprites: SparseColumn<&str> = SparseColumn::new();
+    let mut health: SparseColumn<i32> = SparseColumn::new();
+
+    let player = entities.spawn();
+    let wall = entities.spawn();
+    let enemy = entities.spawn();
+    let marker = entities.spawn();
+
+    positions.insert(player, P2::new(0., 0.));
+    positions.insert(wall, P2::new(16., 0.));
+    positions.insert(enemy, P2::new(32., 0.));
+    sprites.insert(player, "player");
+    sprites.insert(wall, "wall");
+    sprites.insert(enemy, "enemy");
+    sprites.insert(marker, "marker");
+    health.insert(player, 10);
+    health.insert(enemy, 2);
+
+    let mut drawn: Vec<_> = join(&entities, &sprites, &positions)
+        .map(|(_, &sprite, &pos)| (sprite, pos.x))
+        .collect();
+    drawn.sort_by(|a, b| a.0.cmp(b.0));
+    assert_eq!(drawn, vec![("enemy", 32.), ("player", 0.), ("wall", 16.)]);
+
+    let mut alive: Vec<_> = join3(&entities, &health, &positions, &sprites)
+        .map(|(entity, &hp, _, &sprite)| (entity, hp, sprite))
+        .collect();
+    alive.sort();
+    assert_eq!(alive, vec![(player, 10, "player"), (enemy, 2, "enemy")]);
+
+    for (_, pos, _) in join_mut(&entities, &mut positions, &health) {
+        pos.y += 8.;
+    }
+    assert_eq!(positions.get(player), Some(&P2::new(0., 8.)));
+    assert_eq!(positions.get(wall), Some(&P2::new(16., 0.)));
+    assert_eq!(positions.get(enemy), Some(&P2::new(32., 8.)));
+
+    for (_, hp, _, &sprite) in join3_mut(&entities, &mut health, &positions, &sprites) {
+        if sprite == "enemy" {
+            *hp -= 2;
+        }
+    }
+    assert_eq!(health.get(enemy), Some(&0));
+    assert_eq!(health.get(player), Some(&10));
+}
+
+#[test]
+fn cloned_columns_are_rebound_to_cloned_entities() {
+    let mut entities = Entities::new();
+    let mut positions: DenseColumn<P2> = DenseColumn::new();
+    let mut health: SparseColumn<i32> = SparseColumn::new();
+
+    let player = entities.spawn();
+    let enemy = entities.spawn();
+    let wall = entities.spawn();
+    positions.insert(player, P2::new(1., 1.));
+    positions.insert(enemy, P2::new(2., 2.));
+    positions.insert(wall, P2::new(3., 3.));
+    health.insert(player, 10);
+    health.insert(enemy, 3);
+    health.insert(wall, 99);
+    // Despawned without passing in the columns, so its components are left behind
+    entities.despawn(wall, &mut []);
+
+    let entities_cloned = entities.clone();
+    let mut positions_cloned = positions.clone();
+    let mut health_cloned = health.clone();
+    assert_eq!(
+        join(&entities_cloned, &health_cloned, &positions_cloned).count(),
+        0
+    );
+
+    entities_cloned.rebind_columns(&mut [&mut positions_cloned, &mut health_cloned]);
+    let mut joined: Vec<_> = join(&entities_cloned, &health_cloned, &positions_cloned)
+        .map(|(_, &hp, &pos)| (hp, pos.x))
+        .collect();
+    joined.sort_by_key(|a| a.0);
+    assert_eq!(joined, vec![(3, 2.), (10, 1.)]);
+    // Leftover components don't make it across
+    assert_eq!(positions_cloned.len(), 2);
+    assert_eq!(health_cloned.len(), 2);
+
+    let player_cloned = entities_cloned.rebind(player).unwrap();
+    assert_eq!(health_cloned.get(player_cloned), Some(&10));
+    assert_eq!(entities_cloned.rebind(wall), None);
+    // The originals are left as they were
+    assert_eq!(join(&entities, &health, &positions).count(), 2);
+    assert_eq!(health.get(player), Some(&10));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn entities_can_be_serialized_along_with_their_columns() {
+    let mut entities = Entities::new();
+    let mut positions: DenseColumn<P2> = DenseColumn::new();
+    let mut sprites: SparseColumn<String> = SparseColumn::new();
+
+    let handles: Vec<_> = (0..4).map(|_| entities.spawn()).collect();
+    for (i, &entity) in handles.iter().enumerate() {
+        positions.insert(entity, P2::new(i as f32, 0.));
+        if i % 2 == 0 {
+            sprites.insert(entity, format!("sprite{}", i));
+        }
+    }
+    entities.despawn(handles[2], &mut [&mut positions, &mut sprites]);
+
+    let json = serde_json::to_string(&(&entities, &positions, &sprites, &handles)).unwrap();
+    let (entities_loaded, mut positions_loaded, mut sprites_loaded, handles_saved): (
+        Entities,
+        DenseColumn<P2>,
+        SparseColumn<String>,
+        Vec<game2d::components::EntityHandle>,
+    ) = serde_json::from_str(&json).unwrap();
+    entities_loaded.rebind_columns(&mut [&mut positions_loaded, &mut sprites_loaded]);
+
+    let joined: Vec<_> = join(&entities_loaded, &sprites_loaded, &positions_loaded)
+        .map(|(_, sprite, &pos)| (sprite.clone(), pos.x))
+        .collect();
+    assert_eq!(joined, vec![("sprite0".to_string(), 0.)]);
+
+    let handles_loaded: Vec<_> = handles_saved
+        .iter()
+        .map(|&entity| entities_loaded.rebind(entity))
+        .collect();
+    assert_eq!(handles_loaded[2], None);
+    let loaded_positions: Vec<_> = handles_loaded
+        .iter()
+        .filter_map(|&entity| positions_loaded.get(entity?))
+        .map(|pos| pos.x)
+        .collect();
+    assert_eq!(loaded_positions, vec![0., 1., 3.]);
+}
@@ -71,6 +71,21 @@ fn item_regions_can_overlap() {
     assert_set_contains_exactly(id_grid.query(GridRegion::square(4, 4)), &[2]);
 }
 
+#[test]
+fn can_query_into_a_reused_buffer() {
+    let mut id_grid: Grid<i32> = Grid::new();
+    id_grid.insert(3, GridRegion::new([1, 2], [3, 0]));
+    id_grid.insert(1, GridRegion::new([4, 2], [0, 2]));
+    id_grid.insert(2, GridRegion::square(4, 4));
+
+    // Items come out sorted, and only once each, however many squares they cover
+    let mut items = vec![7, 8, 9];
+    id_grid.query_into(GridRegion::new([1, 2], [3, 2]), &mut items);
+    assert_eq!(items, vec![1, 2, 3]);
+    id_grid.query_into(GridRegion::square(0, 0), &mut items);
+    assert_eq!(items.is_empty(), true);
+}
+
 #[test]
 fn subsequent_calls_to_insert_overwrite_previous() {
     let mut id_grid: Grid<i32> = Grid::new();
@@ -102,3 +117,19 @@ fn multiple_calls_to_remove_are_harmless() {
     id_grid.remove(1);
     assert_eq!(id_grid.query(a_region).contains(&1), false);
 }
+
+#[cfg(feature = "serde")]
+#[test]
+fn grids_can_be_serialized() {
+    let mut id_grid: Grid<i32> = Grid::new();
+    id_grid.insert(1, GridRegion::new([0, 0], [2, 2]));
+    id_grid.insert(2, GridRegion::square(5, 5));
+
+    let json = serde_json::to_string(&id_grid).unwrap();
+    let id_grid_loaded: Grid<i32> = serde_json::from_str(&json).unwrap();
+    assert_set_contains_exactly(id_grid_loaded.query(GridRegion::square(1, 1)), &[1]);
+    assert_set_contains_exactly(
+        id_grid_loaded.query(GridRegion::new([0, 0], [5, 5])),
+        &[1, 2],
+    );
+}
@@ -0,0 +1,73 @@
+use game2d::collide::layers::{InvalidLayer, Layer, LayerMask, LayerRegistry};
+use std::convert::TryFrom;
+
+#[test]
+fn layers_must_be_a_single_bit() {
+    assert_eq!(Layer::try_from(1 << 5), Ok(Layer::new(5)));
+    assert_eq!(Layer::try_from(0), Err(InvalidLayer(0)));
+    assert_eq!(Layer::try_from(0b101), Err(InvalidLayer(0b101)));
+    assert_eq!(Layer::new(31).index(), 31);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn deserialized_layers_must_be_a_single_bit() {
+    let layer: Layer =
+        serde_json::from_str(&serde_json::to_string(&Layer::new(5)).unwrap()).unwrap();
+    assert_eq!(layer, Layer::new(5));
+    assert_eq!(serde_json::from_str::<Layer>("0").is_err(), true);
+    assert_eq!(serde_json::from_str::<Layer>("5").is_err(), true);
+}
+
+#[test]
+#[should_panic(expected = "Invalid layer index")]
+fn layer_index_must_be_in_range() {
+    Layer::new(32);
+}
+
+#[test]
+fn layer_masks_can_be_combined() {
+    let (a, b, c) = (Layer::new(0), Layer::new(1), Layer::new(2));
+    let mask = a | b;
+
+    assert_eq!(mask.contains(a), true);
+    assert_eq!(mask.contains(a | c), false);
+    assert_eq!(mask.intersects(a | c), true);
+    assert_eq!(mask.intersects(c), false);
+    assert_eq!(mask & (b | c), LayerMask::from(b));
+    assert_eq!(mask - a, LayerMask::from(b));
+    assert_eq!((!mask).contains(c), true);
+    assert_eq!(mask.iter().collect::<Vec<_>>(), vec![a, b]);
+    assert_eq!(LayerMask::NONE.is_empty(), true);
+    assert_eq!(LayerMask::default(), LayerMask::NONE);
+}
+
+#[test]
+fn layers_can_be_looked_up_by_name() {
+    let mut layers = LayerRegistry::new();
+    layers.register(Layer::new(3), "wall");
+    let actor = layers.register_next("actor");
+
+    assert_eq!(actor, Layer::new(0));
+    assert_eq!(layers.get("wall"), Some(Layer::new(3)));
+    assert_eq!(layers.get("enemy"), None);
+    assert_eq!(layers.name(actor), Some("actor"));
+    assert_eq!(layers.name(Layer::new(1)), None);
+    assert_eq!(layers.mask(), actor | Layer::new(3));
+}
+
+#[test]
+#[should_panic(expected = "Layer already registered: Layer(1)")]
+fn layers_cant_be_registered_twice() {
+    let mut layers = LayerRegistry::new();
+    layers.register(Layer::new(1), "wall");
+    layers.register(Layer::new(1), "actor");
+}
+
+#[test]
+#[should_panic(expected = "Layer name already registered: wall")]
+fn layer_names_cant_be_registered_twice() {
+    let mut layers = LayerRegistry::new();
+    layers.register(Layer::new(0), "wall");
+    layers.register(Layer::new(1), "wall");
+}
@@ -207,3 +207,214 @@ fn can_iterate_handles() {
     assert_eq!(entries.next(), Some(&10));
     assert_eq!(entries.next(), None);
 }
+
+#[test]
+fn can_iterate_entries_with_handles() {
+    let mut pool: Pool<i32> = Pool::new();
+    let handle_a = pool.push(1);
+    let handle_b = pool.push(2);
+    let handle_c = pool.push(3);
+    pool.remove(handle_b);
+
+    let entries: Vec<_> = pool.iter_with_handles().collect();
+    assert_eq!(entries, vec![(handle_a, &1), (handle_c, &3)]);
+
+    for (handle, value) in pool.iter_mut_with_handles() {
+        if handle == handle_c {
+            *value *= 10;
+        }
+    }
+    assert_eq!(pool.get(handle_c), Some(&30));
+}
+
+#[test]
+fn can_retain_entries() {
+    let mut pool: Pool<i32> = Pool::new();
+    let handles: Vec<_> = (1..=6).map(|i| pool.push(i)).collect();
+
+    pool.retain(|handle, value| handle != handles[0] && value % 2 == 1);
+    assert_eq!(pool.len(), 2);
+    assert_eq!(pool.iter().collect::<Vec<_>>(), vec![&3, &5]);
+    assert_eq!(pool.contains(handles[0]), false);
+    assert_eq!(pool.contains(handles[1]), false);
+    assert_eq!(pool.contains(handles[2]), true);
+}
+
+#[test]
+fn can_drain_and_clear_entries() {
+    let mut pool: Pool<i32> = Pool::new();
+    let handle_a = pool.push(1);
+    let handle_b = pool.push(2);
+
+    let drained: Vec<_> = pool.drain().collect();
+    assert_eq!(drained, vec![(handle_a, 1), (handle_b, 2)]);
+    assert_eq!(pool.is_empty(), true);
+    assert_eq!(pool.contains(handle_a), false);
+
+    // Old handles stay invalid after their slots are reused
+    let handle_c = pool.push(3);
+    pool.push(4);
+    assert_eq!(pool.get(handle_a), None);
+    assert_eq!(pool.get(handle_b), None);
+
+    pool.clear();
+    assert_eq!(pool.is_empty(), true);
+    assert_eq!(pool.iter().count(), 0);
+    assert_eq!(pool.contains(handle_c), false);
+    pool.push(5);
+    assert_eq!(pool.len(), 1);
+}
+
+#[test]
+fn shrinking_keeps_live_handles() {
+    let mut pool: Pool<i32> = Pool::with_capacity(10);
+    let handles: Vec<_> = (0..10).map(|i| pool.push(i)).collect();
+    for &handle in handles[2..].iter() {
+        pool.remove(handle);
+    }
+    pool.remove(handles[0]);
+
+    pool.shrink_to_fit();
+    assert_eq!(pool.capacity() < 10, true);
+    assert_eq!(pool.len(), 1);
+    assert_eq!(pool.get(handles[1]), Some(&1));
+
+    // Slots given back and then reused don't bring back the old handles to them
+    let new_handles: Vec<_> = (10..20).map(|i| pool.push(i)).collect();
+    for &handle in handles.iter().filter(|&&handle| handle != handles[1]) {
+        assert_eq!(pool.get(handle), None);
+        assert_eq!(new_handles.contains(&handle), false);
+    }
+    assert_eq!(pool.get(new_handles[9]), Some(&19));
+}
+
+#[test]
+fn compacting_remaps_moved_handles() {
+    let mut pool: Pool<&str> = Pool::with_capacity(10);
+    let handle_a = pool.push("a");
+    let handle_b = pool.push("b");
+    let handle_c = pool.push("c");
+    let handle_d = pool.push("d");
+    pool.remove(handle_a);
+    pool.remove(handle_c);
+
+    let mut remapped = Vec::new();
+    pool.compact(|old, new| remapped.push((old, new)));
+    assert_eq!(pool.capacity() < 10, true);
+    assert_eq!(pool.iter().collect::<Vec<_>>(), vec![&"b", &"d"]);
+
+    assert_eq!(remapped.len(), 2);
+    assert_eq!(remapped[0].0, handle_b);
+    assert_eq!(remapped[1].0, handle_d);
+    for &(old, new) in remapped.iter() {
+        assert_eq!(pool.get(old), None);
+        assert_eq!(pool.get(new).is_some(), true);
+    }
+    assert_eq!(pool.get(remapped[1].1), Some(&"d"));
+
+    // Compacting a pool that's all gaps leaves it empty, but still usable
+    pool.clear();
+    pool.compact(|_, _| panic!("Nothing to move"));
+    assert_eq!(pool.is_empty(), true);
+    let handle_e = pool.push("e");
+    assert_eq!(pool.get(handle_e), Some(&"e"));
+    assert_eq!(pool.get(remapped[0].1), None);
+}
+
+#[test]
+fn cloned_pools_dont_share_handles() {
+    let mut pool: Pool<u32> = Pool::with_capacity(2);
+    let handle_a = pool.push(1);
+    let handle_b = pool.push(2);
+    pool.remove(handle_a);
+
+    let mut pool_cloned = pool.clone();
+    assert_eq!(pool_cloned.get(handle_a), None);
+    assert_eq!(pool_cloned.get(handle_b), None);
+    let (handle_b_cloned, &value) = pool_cloned.iter_with_handles().next().unwrap();
+    assert_eq!(value, 2);
+    assert_eq!(pool.get(handle_b_cloned), None);
+
+    // Handles have to be rebound to be used with the clone, and only ones still in it are
+    let handle_b_rebound = pool_cloned.rebind(handle_b).unwrap();
+    assert_eq!(pool_cloned.get(handle_b_rebound), Some(&2));
+    assert_eq!(pool.rebind(handle_b_cloned), Some(handle_b));
+    assert_eq!(pool_cloned.rebind(handle_a), None);
+
+    // Once the two pools go their separate ways, neither accepts the other's handles
+    let handle_c = pool.push(3);
+    let handle_c_cloned = pool_cloned.push(4);
+    assert_eq!(pool_cloned.get(handle_c), None);
+    assert_eq!(pool.get(handle_c_cloned), None);
+}
+
+#[test]
+fn reused_slots_reject_old_handles() {
+    let mut pool: Pool<u32> = Pool::with_capacity(1);
+    let handles: Vec<_> = (0..100)
+        .map(|i| {
+            let handle = pool.push(i);
+            pool.remove(handle);
+            handle
+        })
+        .collect();
+    let handle_last = pool.push(100);
+
+    assert_eq!(pool.capacity(), 1);
+    for handle in handles {
+        assert_eq!(pool.get(handle), None);
+        assert_ne!(handle, handle_last);
+    }
+    assert_eq!(pool.get(handle_last), Some(&100));
+}
+
+#[test]
+fn handles_from_other_pools_are_rejected() {
+    let mut pool_a: Pool<u32> = Pool::with_capacity(2);
+    let mut pool_b: Pool<u32> = Pool::with_capacity(2);
+    let handle_a = pool_a.push(1);
+    let handle_b = pool_b.push(2);
+    // Even pointing past the end of the other pool is fine
+    let handle_far = (0..10).map(|i| pool_a.push(i)).last().unwrap();
+
+    assert_eq!(pool_b.get(handle_a), None);
+    assert_eq!(pool_b.get_mut(handle_a), None);
+    assert_eq!(pool_b.get(handle_far), None);
+    assert_eq!(pool_b.remove(handle_a), None);
+    assert_eq!(pool_b.len(), 1);
+    assert_eq!(pool_a.get(handle_b), None);
+    assert_ne!(handle_a, handle_b);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn pools_can_be_serialized() {
+    let mut pool: Pool<u32> = Pool::with_capacity(2);
+    let handle_a = pool.push(1);
+    let handle_b = pool.push(2);
+    pool.remove(handle_a);
+
+    let json = serde_json::to_string(&pool).unwrap();
+    let mut pool_loaded: Pool<u32> = serde_json::from_str(&json).unwrap();
+    assert_eq!(pool_loaded.get(handle_a), None);
+    assert_eq!(pool_loaded.iter().collect::<Vec<_>>(), vec![&2]);
+
+    // A loaded pool is a different pool, and so are any others created since, however many
+    // pools there were when it was saved; saved handles don't belong to any of them
+    assert_eq!(pool_loaded.get(handle_b), None);
+    assert_eq!(pool.get(pool_loaded.handles().next().unwrap()), None);
+    let handle_b_loaded: game2d::pool::Handle<u32> =
+        serde_json::from_str(&serde_json::to_string(&handle_b).unwrap()).unwrap();
+    assert_eq!(pool.get(handle_b_loaded), None);
+    assert_eq!(pool_loaded.get(handle_b_loaded), None);
+
+    // ...until they're rebound, which checks that they still point to something
+    let handle_b_rebound = pool_loaded.rebind(handle_b_loaded).unwrap();
+    assert_eq!(pool_loaded.get(handle_b_rebound), Some(&2));
+    assert_eq!(pool_loaded.rebind(handle_a), None);
+
+    // Growing the pool after loading it still works
+    let handles: Vec<_> = (3..10).map(|i| pool_loaded.push(i)).collect();
+    assert_eq!(pool_loaded.len(), 8);
+    assert_eq!(pool_loaded.get(handles[6]), Some(&9));
+}
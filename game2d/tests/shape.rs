@@ -0,0 +1,386 @@
+use game2d::{
+    geom::{P2, V2},
+    shape::{Ray, Rect, RectSide, RectSides, Shape},
+};
+
+mod test_support;
+use crate::test_support::*;
+
+fn rect(x: f32, y: f32, w: f32, h: f32) -> Rect {
+    Rect::new(P2::new(x, y), V2::new(w, h))
+}
+
+#[test]
+fn sweep_reports_time_and_side_of_impact() {
+    let wall = rect(50., 0., 10., 10.);
+
+    let hit = rect(0., 0., 10., 10.)
+        .sweep(V2::new(80., 0.), &wall)
+        .unwrap();
+    assert_eq_f32(hit.time, 0.5, 0.001);
+    assert_eq!(hit.side, RectSide::Left);
+    assert_eq!(hit.normal, V2::new(-1., 0.));
+
+    let hit = rect(100., 0., 10., 10.)
+        .sweep(V2::new(-80., 0.), &wall)
+        .unwrap();
+    assert_eq_f32(hit.time, 0.5, 0.001);
+    assert_eq!(hit.side, RectSide::Right);
+    assert_eq!(hit.normal, V2::new(1., 0.));
+
+    let hit = rect(50., -50., 10., 10.)
+        .sweep(V2::new(0., 80.), &wall)
+        .unwrap();
+    assert_eq_f32(hit.time, 0.5, 0.001);
+    assert_eq!(hit.side, RectSide::Top);
+    assert_eq!(hit.normal, V2::new(0., -1.));
+
+    let hit = rect(50., 50., 10., 10.)
+        .sweep(V2::new(0., -80.), &wall)
+        .unwrap();
+    assert_eq_f32(hit.time, 0.5, 0.001);
+    assert_eq!(hit.side, RectSide::Bottom);
+    assert_eq!(hit.normal, V2::new(0., 1.));
+}
+
+#[test]
+fn sweep_catches_targets_thinner_than_the_movement() {
+    let thin_wall = rect(50., 0., 1., 10.);
+    let mover = rect(0., 0., 10., 10.);
+
+    // The end position is entirely past the wall, so a simple overlap check would miss it
+    let end = Rect::new(mover.pos + V2::new(1000., 0.), mover.size);
+    assert_eq!(end.overlaps(&thin_wall), false);
+
+    let hit = mover.sweep(V2::new(1000., 0.), &thin_wall).unwrap();
+    assert_eq_f32(hit.time, 0.04, 0.001);
+    assert_eq!(hit.side, RectSide::Left);
+}
+
+#[test]
+fn sweep_picks_the_side_entered_last_when_moving_diagonally() {
+    let wall = rect(20., 20., 20., 20.);
+
+    // Starts above and to the left; lines up horizontally first, then hits the top
+    let hit = rect(10., 0., 10., 10.)
+        .sweep(V2::new(20., 20.), &wall)
+        .unwrap();
+    assert_eq_f32(hit.time, 0.5, 0.001);
+    assert_eq!(hit.side, RectSide::Top);
+}
+
+#[test]
+fn sweep_misses_targets_out_of_the_way() {
+    let wall = rect(50., 0., 10., 10.);
+
+    // Moving away
+    assert_eq!(
+        rect(0., 0., 10., 10.)
+            .sweep(V2::new(-100., 0.), &wall)
+            .is_none(),
+        true
+    );
+    // Stopping short
+    assert_eq!(
+        rect(0., 0., 10., 10.)
+            .sweep(V2::new(30., 0.), &wall)
+            .is_none(),
+        true
+    );
+    // Sliding past an edge we're exactly lined up with
+    assert_eq!(
+        rect(0., 10., 10., 10.)
+            .sweep(V2::new(100., 0.), &wall)
+            .is_none(),
+        true
+    );
+    // Not moving at all
+    assert_eq!(
+        rect(40., 0., 10., 10.).sweep(V2::zero(), &wall).is_none(),
+        true
+    );
+}
+
+#[test]
+fn sweep_from_touching_reports_immediate_hit() {
+    let wall = rect(50., 0., 10., 10.);
+
+    let hit = rect(40., 0., 10., 10.)
+        .sweep(V2::new(5., 0.), &wall)
+        .unwrap();
+    assert_eq_f32(hit.time, 0., 0.001);
+    assert_eq!(hit.side, RectSide::Left);
+
+    // Moving away from a touching rect is fine, however
+    assert_eq!(
+        rect(40., 0., 10., 10.)
+            .sweep(V2::new(-5., 0.), &wall)
+            .is_none(),
+        true
+    );
+}
+
+#[test]
+fn sweep_ignores_targets_already_overlapped() {
+    let wall = rect(50., 0., 10., 10.);
+
+    assert_eq!(
+        rect(45., 0., 10., 10.)
+            .sweep(V2::new(5., 0.), &wall)
+            .is_none(),
+        true
+    );
+}
+
+#[test]
+fn closest_side_reports_side_being_touched() {
+    let wall = rect(20., 20., 20., 20.);
+
+    assert_eq!(wall.closest_side(&rect(0., 20., 20., 20.)), RectSide::Left);
+    assert_eq!(
+        wall.closest_side(&rect(40., 25., 20., 20.)),
+        RectSide::Right
+    );
+    assert_eq!(wall.closest_side(&rect(15., 0., 20., 20.)), RectSide::Top);
+    assert_eq!(
+        wall.closest_side(&rect(20., 40., 20., 20.)),
+        RectSide::Bottom
+    );
+
+    // Overlapping rects report the side they're closest to escaping from
+    assert_eq!(
+        wall.closest_side(&rect(38., 20., 20., 20.)),
+        RectSide::Right
+    );
+}
+
+#[test]
+fn contains_includes_top_left_edges_only() {
+    let r = rect(10., 10., 10., 10.);
+
+    assert_eq!(r.contains(P2::new(15., 15.)), true);
+    assert_eq!(r.contains(P2::new(10., 10.)), true);
+    assert_eq!(r.contains(P2::new(20., 15.)), false);
+    assert_eq!(r.contains(P2::new(15., 20.)), false);
+    assert_eq!(r.contains(P2::new(5., 15.)), false);
+}
+
+#[test]
+fn rect_sides_can_be_combined() {
+    let sides = RectSides::TOP | RectSides::LEFT;
+
+    assert_eq!(sides.contains(RectSide::Top), true);
+    assert_eq!(sides.contains(RectSide::Left), true);
+    assert_eq!(sides.contains(RectSide::Bottom), false);
+    assert_eq!(sides.inverse(), RectSides::BOTTOM | RectSides::RIGHT);
+    assert_eq!(RectSides::ALL.inverse(), RectSides::NONE);
+    assert_eq!(RectSides::from(RectSide::Right), RectSides::RIGHT);
+}
+
+#[test]
+fn ray_direction_is_normalized() {
+    let ray = Ray::new(P2::new(1., 2.), V2::new(3., 4.));
+    assert_eq!(ray.dir, V2::new(0.6, 0.8));
+    assert_eq!(ray.at(5.), P2::new(4., 6.));
+}
+
+#[test]
+fn ray_intersects_rect() {
+    let r = rect(10., 10., 10., 10.);
+
+    let hit = Ray::new(P2::new(0., 15.), V2::new(1., 0.))
+        .intersect_rect(&r)
+        .unwrap();
+    assert_eq_f32(hit.distance, 10., 0.001);
+    assert_eq!(hit.point, P2::new(10., 15.));
+    assert_eq!(hit.normal, V2::new(-1., 0.));
+
+    let hit = Ray::new(P2::new(15., 30.), V2::new(0., -1.))
+        .intersect_rect(&r)
+        .unwrap();
+    assert_eq_f32(hit.distance, 10., 0.001);
+    assert_eq!(hit.point, P2::new(15., 20.));
+    assert_eq!(hit.normal, V2::new(0., 1.));
+
+    let hit = Ray::new(P2::new(0., 5.), V2::new(1., 1.))
+        .intersect_rect(&r)
+        .unwrap();
+    assert_eq_f32(hit.point.x, 10., 0.001);
+    assert_eq_f32(hit.point.y, 15., 0.001);
+    assert_eq!(hit.normal, V2::new(-1., 0.));
+}
+
+#[test]
+fn ray_starting_inside_rect_intersects_immediately() {
+    let r = rect(10., 10., 10., 10.);
+
+    let hit = Ray::new(P2::new(15., 15.), V2::new(1., 0.))
+        .intersect_rect(&r)
+        .unwrap();
+    assert_eq!(hit.distance, 0.);
+    assert_eq!(hit.point, P2::new(15., 15.));
+    assert_eq!(hit.normal, V2::zero());
+}
+
+#[test]
+fn ray_can_miss_rect() {
+    let r = rect(10., 10., 10., 10.);
+
+    // Pointing the wrong way
+    assert_eq!(
+        Ray::new(P2::new(0., 15.), V2::new(-1., 0.))
+            .intersect_rect(&r)
+            .is_none(),
+        true
+    );
+    // Passing by
+    assert_eq!(
+        Ray::new(P2::new(0., 25.), V2::new(1., 0.))
+            .intersect_rect(&r)
+            .is_none(),
+        true
+    );
+    // Running along the bottom edge
+    assert_eq!(
+        Ray::new(P2::new(0., 20.), V2::new(1., 0.))
+            .intersect_rect(&r)
+            .is_none(),
+        true
+    );
+    // Leaving from the far edge
+    assert_eq!(
+        Ray::new(P2::new(20., 15.), V2::new(1., 0.))
+            .intersect_rect(&r)
+            .is_none(),
+        true
+    );
+}
+
+#[test]
+fn shape_contains_point() {
+    let r = rect(0., 0., 10., 10.);
+
+    // Corners are outside round shapes
+    assert_eq!(Shape::Rect.contains(&r, P2::new(1., 1.)), true);
+    assert_eq!(Shape::Circle.contains(&r, P2::new(1., 1.)), false);
+    assert_eq!(Shape::Circle.contains(&r, P2::new(5., 1.)), true);
+
+    let r = rect(0., 0., 20., 10.);
+    assert_eq!(Shape::Capsule.contains(&r, P2::new(1., 1.)), false);
+    assert_eq!(Shape::Capsule.contains(&r, P2::new(10., 1.)), true);
+    assert_eq!(Shape::Capsule.contains(&r, P2::new(19., 5.)), true);
+}
+
+#[test]
+fn circles_overlap_and_touch() {
+    let a = rect(0., 0., 10., 10.);
+
+    assert_eq!(
+        Shape::Circle.overlaps(&a, Shape::Circle, &rect(9., 0., 10., 10.)),
+        true
+    );
+    assert_eq!(
+        Shape::Circle.overlaps(&a, Shape::Circle, &rect(10., 0., 10., 10.)),
+        false
+    );
+    assert_eq!(
+        Shape::Circle.touches(&a, Shape::Circle, &rect(10., 0., 10., 10.)),
+        true
+    );
+    // Bounding rects overlap diagonally, but the circles don't
+    assert_eq!(
+        Shape::Circle.touches(&a, Shape::Circle, &rect(8., 8., 10., 10.)),
+        false
+    );
+}
+
+#[test]
+fn round_shapes_overlap_rects() {
+    let wall = rect(10., 0., 10., 10.);
+
+    assert_eq!(
+        Shape::Circle.overlaps(&rect(1., 0., 10., 10.), Shape::Rect, &wall),
+        true
+    );
+    assert_eq!(
+        Shape::Rect.overlaps(&wall, Shape::Circle, &rect(1., 0., 10., 10.)),
+        true
+    );
+    assert_eq!(
+        Shape::Circle.touches(&rect(0., 0., 10., 10.), Shape::Rect, &wall),
+        true
+    );
+    // A circle tucked into the corner of a rect doesn't reach it
+    assert_eq!(
+        Shape::Circle.touches(&rect(1., 9., 10., 10.), Shape::Rect, &wall),
+        false
+    );
+    assert_eq!(
+        Shape::Capsule.overlaps(&rect(-15., 2., 30., 6.), Shape::Rect, &wall),
+        true
+    );
+    assert_eq!(
+        Shape::Capsule.touches(&rect(-19.8, 9.8, 30., 6.), Shape::Rect, &wall),
+        false
+    );
+}
+
+#[test]
+fn penetration_pushes_shapes_apart() {
+    let a = rect(0., 0., 10., 10.);
+
+    let push = Shape::Circle
+        .penetration(&a, Shape::Circle, &rect(6., 0., 10., 10.))
+        .unwrap();
+    assert_eq_f32(push.x, -4., 0.001);
+    assert_eq_f32(push.y, 0., 0.001);
+
+    let push = Shape::Circle
+        .penetration(&a, Shape::Rect, &rect(-5., 8., 20., 10.))
+        .unwrap();
+    assert_eq_f32(push.x, 0., 0.001);
+    assert_eq_f32(push.y, -2., 0.001);
+
+    // Centers are inside the rect, so go out the shortest way
+    let push = Shape::Circle
+        .penetration(&a, Shape::Rect, &rect(4., -10., 20., 30.))
+        .unwrap();
+    assert_eq_f32(push.x, -6., 0.001);
+    assert_eq_f32(push.y, 0., 0.001);
+
+    assert_eq!(
+        Shape::Circle
+            .penetration(&a, Shape::Circle, &rect(10., 0., 10., 10.))
+            .is_none(),
+        true
+    );
+}
+
+#[test]
+fn ray_intersects_round_shapes() {
+    let hit = Ray::new(P2::new(0., 15.), V2::new(1., 0.))
+        .intersect_shape(Shape::Circle, &rect(10., 10., 10., 10.))
+        .unwrap();
+    assert_eq_f32(hit.distance, 10., 0.001);
+    assert_eq!(hit.normal, V2::new(-1., 0.));
+
+    // Misses the rounded corner
+    assert_eq!(
+        Ray::new(P2::new(0., 22.), V2::new(1., -1.))
+            .intersect_shape(Shape::Circle, &rect(10., 10., 10., 10.))
+            .is_none(),
+        true
+    );
+
+    let capsule = rect(10., 10., 30., 10.);
+    let hit = Ray::new(P2::new(25., 0.), V2::new(0., 1.))
+        .intersect_shape(Shape::Capsule, &capsule)
+        .unwrap();
+    assert_eq_f32(hit.distance, 10., 0.001);
+    assert_eq!(hit.normal, V2::new(0., -1.));
+    let hit = Ray::new(P2::new(50., 15.), V2::new(-1., 0.))
+        .intersect_shape(Shape::Capsule, &capsule)
+        .unwrap();
+    assert_eq_f32(hit.distance, 10., 0.001);
+    assert_eq_f32(hit.normal.x, 1., 0.001);
+}
@@ -0,0 +1,289 @@
+use game2d::{
+    collide::layers::Layer,
+    collide::tiles::{TileFlags, TileLayer},
+    geom::{P2, V2},
+    shape::{Ray, Rect, RectSides},
+};
+
+mod test_support;
+use crate::test_support::*;
+
+fn rect(x: f32, y: f32, w: f32, h: f32) -> Rect {
+    Rect::new(P2::new(x, y), V2::new(w, h))
+}
+
+/// Create a layer of 10x10 tiles from a picture, where `#` is solid, `~` is water, `O` is a hole,
+/// `=` is a ledge, and anything else is empty.
+fn layer_from(rows: &[&str]) -> TileLayer {
+    let num_tiles = (rows[0].len() as u16, rows.len() as u16);
+    TileLayer::new(
+        Layer::new(0),
+        V2::new(10., 10.),
+        num_tiles,
+        |x, y| match rows[y as usize].as_bytes()[x as usize] {
+            b'#' => TileFlags::SOLID,
+            b'~' => TileFlags::WATER,
+            b'O' => TileFlags::HOLE,
+            b'=' => TileFlags::LEDGE,
+            _ => TileFlags::EMPTY,
+        },
+    )
+}
+
+#[test]
+#[should_panic(expected = "Invalid tile size: V2 { x: 0.0, y: 10.0 }")]
+fn tile_size_must_be_positive() {
+    TileLayer::new(Layer::new(0), V2::new(0., 10.), (1, 1), |_, _| {
+        TileFlags::EMPTY
+    });
+}
+
+#[test]
+fn tile_flags_can_be_combined() {
+    let flags = TileFlags::SOLID | TileFlags::WATER;
+
+    assert_eq!(flags.contains(TileFlags::SOLID), true);
+    assert_eq!(flags.contains(TileFlags::SOLID | TileFlags::HOLE), false);
+    assert_eq!(flags.intersects(TileFlags::SOLID | TileFlags::HOLE), true);
+    assert_eq!(flags.intersects(TileFlags::LEDGE), false);
+    assert_eq!(TileFlags::EMPTY.is_empty(), true);
+    assert_eq!(TileFlags::default(), TileFlags::EMPTY);
+}
+
+#[test]
+fn can_get_and_set_tiles() {
+    let mut layer = layer_from(&["#.", ".~"]);
+
+    assert_eq!(layer.num_tiles(), (2, 2));
+    assert_eq!(layer.get(0, 0), TileFlags::SOLID);
+    assert_eq!(layer.get(1, 0), TileFlags::EMPTY);
+    assert_eq!(layer.get(1, 1), TileFlags::WATER);
+    assert_eq!(layer.get(5, 5), TileFlags::EMPTY); // Out of bounds
+
+    layer.set(1, 0, TileFlags::HOLE);
+    assert_eq!(layer.get(1, 0), TileFlags::HOLE);
+}
+
+#[test]
+#[should_panic(expected = "Tile out of bounds: (2, 0)")]
+fn cant_set_tiles_out_of_bounds() {
+    let mut layer = layer_from(&["#.", ".~"]);
+    layer.set(2, 0, TileFlags::SOLID);
+}
+
+#[test]
+fn can_query_flags_by_area() {
+    let layer = layer_from(&[
+        "##########",
+        "#........#",
+        "#..~~....#",
+        "#..~~..O.#",
+        "##########",
+    ]);
+
+    assert_eq!(layer.flags_at(P2::new(5., 5.)), TileFlags::SOLID);
+    assert_eq!(layer.flags_at(P2::new(15., 15.)), TileFlags::EMPTY);
+    assert_eq!(layer.flags_at(P2::new(30., 20.)), TileFlags::WATER);
+    assert_eq!(layer.flags_at(P2::new(-5., -5.)), TileFlags::EMPTY);
+
+    assert_eq!(layer.flags_in(&rect(30., 20., 20., 20.)), TileFlags::WATER);
+    // Rects which only touch a tile don't count
+    assert_eq!(layer.flags_in(&rect(30., 10., 20., 10.)), TileFlags::EMPTY);
+    assert_eq!(
+        layer.flags_in(&rect(25., 25., 50., 30.)),
+        TileFlags::SOLID | TileFlags::WATER | TileFlags::HOLE
+    );
+}
+
+#[test]
+fn neighboring_tiles_are_merged() {
+    let layer = layer_from(&[
+        "#####", //
+        "#~~.#", //
+        "#~~.#", //
+        "#####",
+    ]);
+
+    let merged: Vec<(Rect, TileFlags)> = layer.merged_rects().collect();
+    assert_eq!(
+        merged,
+        vec![
+            (rect(0., 0., 50., 10.), TileFlags::SOLID),
+            (rect(0., 10., 10., 30.), TileFlags::SOLID),
+            (rect(10., 10., 20., 20.), TileFlags::WATER),
+            (rect(40., 10., 10., 30.), TileFlags::SOLID),
+            (rect(10., 30., 30., 10.), TileFlags::SOLID),
+        ]
+    );
+}
+
+#[test]
+fn setting_a_tile_remerges_the_layer() {
+    let mut layer = layer_from(&["###"]);
+    assert_eq!(layer.merged_rects().count(), 1);
+
+    layer.set(1, 0, TileFlags::EMPTY);
+    let merged: Vec<(Rect, TileFlags)> = layer.merged_rects().collect();
+    assert_eq!(
+        merged,
+        vec![
+            (rect(0., 0., 10., 10.), TileFlags::SOLID),
+            (rect(20., 0., 10., 10.), TileFlags::SOLID),
+        ]
+    );
+}
+
+#[test]
+fn each_layer_can_be_blocked_by_different_tiles() {
+    let mut layer = layer_from(&["#"]);
+    let (layer_walker, layer_flyer) = (Layer::new(1), Layer::new(2));
+
+    layer.set_blocking(layer_walker, TileFlags::SOLID | TileFlags::WATER);
+    assert_eq!(
+        layer.blocking(layer_walker),
+        TileFlags::SOLID | TileFlags::WATER
+    );
+    assert_eq!(
+        layer.blocking(layer_flyer),
+        TileFlags::SOLID | TileFlags::LEDGE
+    );
+}
+
+#[test]
+fn ledges_only_block_from_some_sides() {
+    let blocking = TileFlags::SOLID | TileFlags::LEDGE;
+
+    assert_eq!(TileFlags::SOLID.blocked_sides(blocking), RectSides::ALL);
+    assert_eq!(
+        TileFlags::LEDGE.blocked_sides(blocking),
+        RectSides::BOTTOM | RectSides::LEFT | RectSides::RIGHT
+    );
+    assert_eq!(
+        (TileFlags::LEDGE | TileFlags::SOLID).blocked_sides(blocking),
+        RectSides::ALL
+    );
+    assert_eq!(TileFlags::WATER.blocked_sides(blocking), RectSides::NONE);
+    assert_eq!(
+        TileFlags::LEDGE.blocked_sides(TileFlags::SOLID),
+        RectSides::NONE
+    );
+}
+
+#[test]
+fn ray_stops_at_first_matching_tile() {
+    let layer = layer_from(&["..........", "...~...#..", ".........."]);
+
+    let hit = layer
+        .raycast(
+            Ray::new(P2::new(0., 15.), V2::new(1., 0.)),
+            1000.,
+            TileFlags::SOLID,
+        )
+        .unwrap();
+    assert_eq_f32(hit.distance, 70., 0.001);
+    assert_eq!(hit.normal, V2::new(-1., 0.));
+
+    let hit = layer
+        .raycast(
+            Ray::new(P2::new(0., 15.), V2::new(1., 0.)),
+            1000.,
+            TileFlags::SOLID | TileFlags::WATER,
+        )
+        .unwrap();
+    assert_eq_f32(hit.distance, 30., 0.001);
+
+    // Coming in from outside the layer
+    let hit = layer
+        .raycast(
+            Ray::new(P2::new(75., -50.), V2::new(0., 1.)),
+            1000.,
+            TileFlags::SOLID,
+        )
+        .unwrap();
+    assert_eq!(hit.point, P2::new(75., 10.));
+    assert_eq!(hit.normal, V2::new(0., -1.));
+}
+
+#[test]
+fn ray_can_miss_tiles() {
+    let layer = layer_from(&["..........", ".......#..", ".........."]);
+
+    // Too short
+    assert_eq!(
+        layer
+            .raycast(
+                Ray::new(P2::new(0., 15.), V2::new(1., 0.)),
+                50.,
+                TileFlags::SOLID
+            )
+            .is_none(),
+        true
+    );
+    // Passing by
+    assert_eq!(
+        layer
+            .raycast(
+                Ray::new(P2::new(0., 25.), V2::new(1., 0.)),
+                1000.,
+                TileFlags::SOLID
+            )
+            .is_none(),
+        true
+    );
+    // Never entering the layer
+    assert_eq!(
+        layer
+            .raycast(
+                Ray::new(P2::new(0., -15.), V2::new(-1., -1.)),
+                1000.,
+                TileFlags::SOLID
+            )
+            .is_none(),
+        true
+    );
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn tile_layers_can_be_serialized() {
+    let mut tiles = layer_from(&["##~", "#..", "==O"]);
+    tiles.set_blocking(Layer::new(1), TileFlags::WATER);
+
+    let json = serde_json::to_string(&tiles).unwrap();
+    let tiles_loaded: TileLayer = serde_json::from_str(&json).unwrap();
+    assert_eq!(tiles_loaded.num_tiles(), (3, 3));
+    assert_eq!(tiles_loaded.get(2, 0), TileFlags::WATER);
+    assert_eq!(tiles_loaded.blocking(Layer::new(1)), TileFlags::WATER);
+    assert_eq!(
+        tiles_loaded.merged_rects().collect::<Vec<_>>(),
+        tiles.merged_rects().collect::<Vec<_>>()
+    );
+    assert_eq!(serde_json::to_string(&tiles_loaded).unwrap(), json);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn deserialized_tile_layers_must_fit_their_size() {
+    let json = serde_json::to_value(layer_from(&["##", "#."])).unwrap();
+    // Merged rects are rebuilt on load, so they're never saved
+    assert_eq!(json.get("merged").is_none(), true);
+
+    let load = |change: &dyn Fn(&mut serde_json::Value)| {
+        let mut json = json.clone();
+        change(&mut json);
+        serde_json::from_value::<TileLayer>(json).map(|tiles| tiles.num_tiles())
+    };
+    assert_eq!(load(&|_| {}).unwrap(), (2, 2));
+
+    let error = load(&|json| json["num_tiles"] = serde_json::json!([3, 2])).err();
+    assert_eq!(
+        error.unwrap().to_string(),
+        "Invalid tile layer: 4 flags for (3, 2) tiles of size V2 { x: 10.0, y: 10.0 }"
+    );
+    let pop_flag = |json: &mut serde_json::Value| {
+        json["flags"].as_array_mut().unwrap().pop();
+    };
+    assert_eq!(load(&pop_flag).is_err(), true);
+    let zero_size = |json: &mut serde_json::Value| json["tile_size"]["x"] = 0.into();
+    assert_eq!(load(&zero_size).is_err(), true);
+}
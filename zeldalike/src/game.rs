@@ -6,8 +6,10 @@ use ggez::graphics::{self, Color, DrawMode, Image, Rect};
 use ggez::timer;
 use ggez::{Context, GameResult};
 
+use game2d::collide::layers::{Layer, LayerRegistry};
+use game2d::collide::tiles::{TileFlags, TileLayer};
 use game2d::collide::CollisionWorldParams;
-use game2d::collide::{BodyHandle, CollisionWorld};
+use game2d::collide::{Body, BodyHandle, CollisionWorld};
 use game2d::geom::V2;
 use game2d::ggez::sprite::Sprite;
 use game2d::ggez::sprite::SpriteParams;
@@ -123,8 +125,8 @@ impl Entity {
     }
 }
 
-const GROUP_WALL: u32 = game2d::collide::GROUP_0;
-const GROUP_PLYR: u32 = game2d::collide::GROUP_1;
+const LAYER_WALL: Layer = Layer::new(0);
+const LAYER_PLYR: Layer = Layer::new(1);
 
 /// Collection of ALL state related to rendering the game - essentially,
 /// represents the game world.
@@ -150,15 +152,20 @@ impl GameState {
             num_tiles: (1, 1),
         });
 
+        let mut layers = LayerRegistry::new();
+        layers.register(LAYER_WALL, "wall");
+        layers.register(LAYER_PLYR, "player");
+
         let mut collision_world = CollisionWorld::new(CollisionWorldParams {
-            group_pairs: vec![[GROUP_WALL, GROUP_PLYR]],
-            partition_size: [20., 20.],
+            layer_pairs: vec![[LAYER_WALL, LAYER_PLYR]],
+            corner_tolerance: 3.,
+            ..CollisionWorldParams::new(layers)
         });
 
         let mut player = Entity::new(Sprite::new(SpriteParams::new(&player_sheet)));
         player.center_on_board(cfg.board_size);
         player.body_handle =
-            Some(collision_world.new_body(GROUP_PLYR, player.sprite.pos, player.sprite.size()));
+            Some(collision_world.new_body(LAYER_PLYR, player.sprite.pos, player.sprite.size()));
 
         let mut walls: Vec<Entity> = Vec::new();
 
@@ -190,9 +197,22 @@ impl GameState {
             walls.push(wall)
         }
 
-        for wall in &walls {
-            collision_world.new_body(GROUP_WALL, wall.sprite.pos, wall.sprite.size());
-        }
+        collision_world.set_tiles(TileLayer::new(
+            LAYER_WALL,
+            cfg.tile_size,
+            (num_tiles_x as u16, num_tiles_y as u16),
+            |x, y| {
+                let is_edge = x == 0
+                    || y == 0
+                    || i32::from(x) == num_tiles_x - 1
+                    || i32::from(y) == num_tiles_y - 1;
+                if is_edge {
+                    TileFlags::SOLID
+                } else {
+                    TileFlags::EMPTY
+                }
+            },
+        ));
 
         Ok(GameState {
             debug: DebugSettings::default(),
@@ -204,6 +224,15 @@ impl GameState {
     }
 
     fn render_collision_outlines(&mut self, ctx: &mut Context) {
+        if let Some(tiles) = self.collision_world.tiles() {
+            for (rect, _) in tiles.merged_rects() {
+                let _ = graphics::rectangle(
+                    ctx,
+                    DrawMode::Line(0.25),
+                    Rect::new(rect.pos.x, rect.pos.y, rect.size.x, rect.size.y),
+                );
+            }
+        }
         for body in self.collision_world.bodies() {
             let _ = graphics::rectangle(
                 ctx,
@@ -213,7 +242,12 @@ impl GameState {
         }
 
         let player_handle = self.player.body_handle.unwrap();
-        let mut touching = self.collision_world.get_touching(player_handle);
+        let mut touching: Vec<&Body> = self
+            .collision_world
+            .get_touching(player_handle)
+            .into_iter()
+            .map(|(_, body)| body)
+            .collect();
         if !touching.is_empty() {
             touching.push(self.collision_world.body(player_handle).unwrap());
             let restore_color = graphics::get_color(ctx);
@@ -234,16 +268,15 @@ impl GameState {
 impl EventHandler for GameState {
     fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
         let player_handle = self.player.body_handle.unwrap();
-        {
-            let body = self.collision_world.body_mut(player_handle).unwrap();
-            body.vel = self.input.move_vec().normalized() * (70.);
-        }
+        self.collision_world
+            .set_vel(player_handle, self.input.move_vec().normalized() * (70.));
 
         self.collision_world.elapse_time(timer::get_delta(ctx));
 
         {
+            let alpha = self.collision_world.interpolation_alpha();
             let body = self.collision_world.body(player_handle).unwrap();
-            self.player.sprite.pos = body.pos;
+            self.player.sprite.pos = body.interpolated_pos(alpha);
         }
 
         Ok(())